@@ -0,0 +1,131 @@
+use std::{
+    fmt,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use snafu::ResultExt;
+use tokio::sync::oneshot;
+
+use crate::{error::SpawnThreadSnafu, worker::WorkerEntry, Error, ShutdownReason, WorkerOptions};
+
+/// A worker which blocks, e.g. because it wraps a synchronous library. It runs
+/// on its own OS thread instead of the tokio worker pool.
+///
+/// The thread cannot be aborted, a worker which is aborted after a timeout or
+/// an escalation is reported as such, but its thread keeps running detached.
+pub trait BlockingWorker {
+    type Error;
+
+    fn name(&self) -> &str;
+
+    fn serve(self, shutdown_token: ShutdownToken) -> Result<(), Self::Error>;
+}
+
+/// Tells a [`BlockingWorker`] that it should shut down, it can be polled or
+/// waited on.
+#[derive(Clone, Default)]
+pub struct ShutdownToken {
+    inner: Arc<(Mutex<Option<ShutdownReason>>, Condvar)>,
+}
+
+impl ShutdownToken {
+    /// Returns the reason of the shutdown, or `None` if the worker is not
+    /// signalled yet.
+    #[inline]
+    #[must_use]
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.inner.0.lock().expect("lock is not poisoned; qed").clone()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_shutdown(&self) -> bool { self.reason().is_some() }
+
+    /// Blocks until the worker is signalled.
+    #[must_use]
+    pub fn wait(&self) -> ShutdownReason {
+        let (reason, condvar) = &*self.inner;
+        let reason = condvar
+            .wait_while(reason.lock().expect("lock is not poisoned; qed"), |reason| {
+                reason.is_none()
+            })
+            .expect("lock is not poisoned; qed");
+        reason.clone().expect("worker is signalled; qed")
+    }
+
+    /// Blocks until the worker is signalled or `timeout` is elapsed, returns
+    /// `None` in the latter case.
+    #[must_use]
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<ShutdownReason> {
+        let (reason, condvar) = &*self.inner;
+        let (reason, _) = condvar
+            .wait_timeout_while(
+                reason.lock().expect("lock is not poisoned; qed"),
+                timeout,
+                |reason| reason.is_none(),
+            )
+            .expect("lock is not poisoned; qed");
+        reason.clone()
+    }
+
+    fn shut_down(&self, shutdown_reason: ShutdownReason) {
+        let (reason, condvar) = &*self.inner;
+        *reason.lock().expect("lock is not poisoned; qed") = Some(shutdown_reason);
+        condvar.notify_all();
+    }
+}
+
+impl fmt::Debug for ShutdownToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownToken").field("reason", &self.reason()).finish()
+    }
+}
+
+impl<E> WorkerEntry<E>
+where
+    E: From<Error> + Send + 'static,
+{
+    /// Runs `worker` on a thread named after it, the returned future resolves
+    /// once the thread is finished and resumes its panic if it panicked. The
+    /// worker fails with [`Error::SpawnThread`] if the thread cannot be
+    /// spawned.
+    pub fn from_blocking_worker(
+        worker: impl BlockingWorker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self {
+        let name = worker.name().to_string();
+        let worker_name = name.clone();
+        Self::new_once(&name, options, move |shutdown_signal| {
+            Box::pin(async move {
+                let shutdown_token = ShutdownToken::default();
+                let (result_tx, mut result_rx) = oneshot::channel();
+                std::thread::Builder::new()
+                    .name(worker_name.clone())
+                    .spawn({
+                        let shutdown_token = shutdown_token.clone();
+                        move || {
+                            let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                worker.serve(shutdown_token)
+                            }));
+                            let _unused = result_tx.send(result);
+                        }
+                    })
+                    .context(SpawnThreadSnafu { worker: worker_name })?;
+
+                let result = tokio::select! {
+                    result = &mut result_rx => result,
+                    reason = shutdown_signal => {
+                        shutdown_token.shut_down(reason);
+                        result_rx.await
+                    }
+                };
+                match result.expect("thread always sends its result; qed") {
+                    Ok(result) => result,
+                    Err(payload) => std::panic::resume_unwind(payload),
+                }
+            })
+        })
+    }
+}
@@ -0,0 +1,88 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::time::Instant;
+
+/// Shuts down all workers gracefully with
+/// [`ShutdownReason::CircuitBreakerTripped`](crate::ShutdownReason::CircuitBreakerTripped)
+/// when more than a number of worker failures occur within a sliding window,
+/// set with
+/// [`with_circuit_breaker`](crate::LifecycleManager::with_circuit_breaker).
+///
+/// Every run of a worker which returns an error or panics counts as a
+/// failure, also if the worker is restarted afterwards.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CircuitBreaker {
+    max_failures: u32,
+    window: Duration,
+}
+
+impl CircuitBreaker {
+    /// Trips when more than `max_failures` failures occur within `window`.
+    #[inline]
+    #[must_use]
+    pub const fn new(max_failures: u32, window: Duration) -> Self { Self { max_failures, window } }
+
+    #[inline]
+    #[must_use]
+    pub const fn max_failures(&self) -> u32 { self.max_failures }
+
+    #[inline]
+    #[must_use]
+    pub const fn window(&self) -> Duration { self.window }
+}
+
+/// The failures within the window of a [`CircuitBreaker`].
+#[derive(Debug)]
+pub(crate) struct FailureWindow {
+    circuit_breaker: CircuitBreaker,
+    failures: VecDeque<Instant>,
+    tripped: bool,
+}
+
+impl FailureWindow {
+    pub fn new(circuit_breaker: CircuitBreaker) -> Self {
+        Self { circuit_breaker, failures: VecDeque::new(), tripped: false }
+    }
+
+    pub const fn circuit_breaker(&self) -> CircuitBreaker { self.circuit_breaker }
+
+    /// Records a failure which occurs now, returns `true` if it trips the
+    /// circuit breaker, which only happens once.
+    pub fn record(&mut self) -> bool {
+        let now = Instant::now();
+        while self
+            .failures
+            .front()
+            .is_some_and(|failed_at| now.duration_since(*failed_at) > self.circuit_breaker.window)
+        {
+            self.failures.pop_front();
+        }
+        self.failures.push_back(now);
+
+        let tripped =
+            !self.tripped && self.failures.len() > self.circuit_breaker.max_failures as usize;
+        self.tripped |= tripped;
+        tripped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CircuitBreaker, FailureWindow};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failure_window() {
+        let mut window = FailureWindow::new(CircuitBreaker::new(2, Duration::from_secs(10)));
+        assert!(!window.record());
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        assert!(!window.record());
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        // the first failure is out of the window
+        assert!(!window.record());
+        assert!(window.record());
+        // it trips only once
+        assert!(!window.record());
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use snafu::ResultExt;
+use tokio::{
+    sync::{broadcast, watch},
+    time::{Interval, MissedTickBehavior},
+};
+
+#[cfg(unix)]
+use crate::ReloadReceiver;
+use crate::{
+    error::{LoadConfigSnafu, ParseConfigSnafu, Result},
+    events::{self, LifecycleEvent},
+    Error, ShutdownSignal, Worker,
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type Parser<C> = Arc<dyn Fn(&str) -> Result<C, BoxError> + Send + Sync>;
+
+/// A worker reloading a config file when it changes or when a reload signal
+/// is received, created by
+/// [`LifecycleManager::config_worker`](crate::LifecycleManager::config_worker).
+///
+/// Every config parsed successfully is published to the receivers of
+/// [`subscribe`](Self::subscribe). If the file could not be read or parsed,
+/// the previous config is kept and the failure is logged and sent as
+/// [`LifecycleEvent::ConfigReloadFailed`].
+///
+/// Changes are noticed by polling the modification time and the size of the
+/// file, every 5 seconds unless
+/// [`with_poll_interval`](Self::with_poll_interval) is set. The file is read
+/// and parsed on the blocking thread pool of tokio.
+///
+/// Reload signals are only received if they are registered with
+/// [`LifecycleManager::with_reload_signal`](crate::LifecycleManager::with_reload_signal),
+/// e.g. without [`ReloadSignal::Hangup`](crate::ReloadSignal::Hangup) a
+/// `SIGHUP` keeps its default action and terminates the process.
+pub struct ConfigWorker<C, E> {
+    name: String,
+    path: PathBuf,
+    parser: Parser<C>,
+    poll_interval: Option<Duration>,
+    version: Option<FileVersion>,
+    config_tx: watch::Sender<Arc<C>>,
+    #[cfg(unix)]
+    reload_receiver: ReloadReceiver,
+    event_tx: broadcast::Sender<LifecycleEvent>,
+    _error: PhantomData<fn() -> E>,
+}
+
+/// What tells whether a file is changed without reading it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileVersion {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self { modified: metadata.modified().ok(), len: metadata.len() })
+    }
+}
+
+impl<C, E> ConfigWorker<C, E>
+where
+    C: Send + Sync + 'static,
+{
+    pub(crate) fn load<F, PE>(
+        path: PathBuf,
+        parser: F,
+        #[cfg(unix)] reload_receiver: ReloadReceiver,
+        event_tx: broadcast::Sender<LifecycleEvent>,
+    ) -> Result<Self>
+    where
+        F: Fn(&str) -> Result<C, PE> + Send + Sync + 'static,
+        PE: std::error::Error + Send + Sync + 'static,
+    {
+        let parser: Parser<C> = Arc::new(move |content| parser(content).map_err(BoxError::from));
+        let version = FileVersion::of(&path);
+        let config = read_config(&path, &parser)?;
+        let (config_tx, _) = watch::channel(Arc::new(config));
+        Ok(Self {
+            name: format!("config-worker:{}", path.display()),
+            path,
+            parser,
+            poll_interval: Some(Duration::from_secs(5)),
+            version,
+            config_tx,
+            #[cfg(unix)]
+            reload_receiver,
+            event_tx,
+            _error: PhantomData,
+        })
+    }
+
+    /// Names the worker `name` instead of `config-worker:` followed by the
+    /// path of the file.
+    #[inline]
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Polls the file for changes every `poll_interval`, or never if it is
+    /// `None`. The config is then only reloaded on reload signals.
+    #[inline]
+    #[must_use]
+    pub fn with_poll_interval(mut self, poll_interval: impl Into<Option<Duration>>) -> Self {
+        self.poll_interval = poll_interval.into().filter(|interval| !interval.is_zero());
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Returns the config which is published last.
+    #[inline]
+    #[must_use]
+    pub fn current(&self) -> Arc<C> { self.config_tx.borrow().clone() }
+
+    /// Returns a receiver of the config, which can be moved into workers.
+    #[inline]
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<Arc<C>> { self.config_tx.subscribe() }
+
+    async fn reload(&mut self, trigger: &str) {
+        let (path, parser) = (self.path.clone(), self.parser.clone());
+        let (version, result) = tokio::task::spawn_blocking(move || {
+            (FileVersion::of(&path), read_config(&path, &parser))
+        })
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+        self.version = version;
+        let path = self.path.clone();
+        match result {
+            Ok(config) => {
+                tracing::info!("Config {} is reloaded on {trigger}", path.display());
+                self.config_tx.send_replace(Arc::new(config));
+                events::emit(&self.event_tx, LifecycleEvent::ConfigReloaded { path });
+            }
+            Err(err) => {
+                tracing::warn!("Keep the previous config, error: {err}");
+                let error = err.to_string();
+                events::emit(&self.event_tx, LifecycleEvent::ConfigReloadFailed { path, error });
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C, E> Worker for ConfigWorker<C, E>
+where
+    C: Send + Sync + 'static,
+    E: From<Error> + Send,
+{
+    type Error = E;
+
+    fn name(&self) -> &str { &self.name }
+
+    async fn serve(mut self, mut shutdown_signal: ShutdownSignal) -> Result<(), Self::Error> {
+        let mut poll = self.poll_interval.map(|poll_interval| {
+            let mut poll = tokio::time::interval(poll_interval);
+            poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            poll
+        });
+        #[cfg(unix)]
+        let mut reload_receiver = self.reload_receiver.clone();
+
+        loop {
+            #[cfg(unix)]
+            let reload_signal = reload_receiver.recv();
+            #[cfg(not(unix))]
+            let reload_signal = std::future::pending::<Option<std::convert::Infallible>>();
+
+            tokio::select! {
+                _ = &mut shutdown_signal => break,
+                () = tick(&mut poll) => {
+                    let path = self.path.clone();
+                    let version = tokio::task::spawn_blocking(move || FileVersion::of(&path))
+                        .await
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+                    if version != self.version {
+                        self.reload("change").await;
+                    }
+                }
+                Some(signal) = reload_signal => self.reload(&signal.to_string()).await,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_config<C>(path: &Path, parser: &Parser<C>) -> Result<C> {
+    let content = fs::read_to_string(path).context(LoadConfigSnafu { path })?;
+    parser(&content).context(ParseConfigSnafu { path })
+}
+
+/// Waits for the next tick of `poll`, or forever if polling is disabled.
+async fn tick(poll: &mut Option<Interval>) {
+    match poll {
+        Some(poll) => {
+            poll.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::error::{DependencyCycleSnafu, Result, UnknownDependencySnafu};
+
+/// Dependencies between workers, indexed by the order in which the workers
+/// were added.
+#[derive(Debug)]
+pub(crate) struct DependencyGraph {
+    dependencies: Vec<Vec<usize>>,
+    dependents: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    /// # Errors
+    ///
+    /// If a worker depends on a name no worker is registered with, or if the
+    /// dependencies contain a cycle.
+    pub fn new<'a>(workers: impl IntoIterator<Item = (&'a str, &'a [String])>) -> Result<Self> {
+        let workers: Vec<_> = workers.into_iter().collect();
+
+        let mut indices_by_name = HashMap::<&str, Vec<usize>>::new();
+        for (index, (name, _)) in workers.iter().enumerate() {
+            indices_by_name.entry(name).or_default().push(index);
+        }
+
+        let mut dependencies = Vec::with_capacity(workers.len());
+        let mut dependents = vec![Vec::new(); workers.len()];
+        for (index, (name, worker_dependencies)) in workers.iter().enumerate() {
+            let mut indices = Vec::new();
+            for dependency in *worker_dependencies {
+                let dependency_indices = indices_by_name
+                    .get(dependency.as_str())
+                    .ok_or_else(|| UnknownDependencySnafu { worker: *name, dependency }.build())?;
+                indices.extend_from_slice(dependency_indices);
+            }
+            indices.sort_unstable();
+            indices.dedup();
+
+            for &dependency in &indices {
+                dependents[dependency].push(index);
+            }
+            dependencies.push(indices);
+        }
+
+        let graph = Self { dependencies, dependents };
+        if let Some(index) = graph.find_cycle() {
+            return DependencyCycleSnafu { worker: workers[index].0 }.fail();
+        }
+
+        Ok(graph)
+    }
+
+    /// Groups workers into the phases they should be shut down in.
+    ///
+    /// A worker nobody depends on is in the first phase, any other worker is
+    /// in the phase right after the last of its dependents.
+    pub fn shutdown_phases(&self) -> Vec<Vec<usize>> { self.layers().0 }
+
+    fn layers(&self) -> (Vec<Vec<usize>>, Vec<usize>) {
+        let mut remaining_dependents: Vec<_> = self.dependents.iter().map(Vec::len).collect();
+        let mut phases = Vec::new();
+        let mut phase: Vec<_> = (0..self.dependencies.len())
+            .filter(|&index| remaining_dependents[index] == 0)
+            .collect();
+
+        while !phase.is_empty() {
+            let mut next_phase = Vec::new();
+            for &index in &phase {
+                for &dependency in &self.dependencies[index] {
+                    remaining_dependents[dependency] -= 1;
+                    if remaining_dependents[dependency] == 0 {
+                        next_phase.push(dependency);
+                    }
+                }
+            }
+            next_phase.sort_unstable();
+            phases.push(std::mem::replace(&mut phase, next_phase));
+        }
+
+        (phases, remaining_dependents)
+    }
+
+    fn find_cycle(&self) -> Option<usize> {
+        let (_, remaining_dependents) = self.layers();
+        let mut index = remaining_dependents.iter().position(|&count| count > 0)?;
+
+        // every worker left over still has a left over dependent, walking along them
+        // must eventually revisit a worker of the cycle
+        let mut visited = vec![false; self.dependencies.len()];
+        while !visited[index] {
+            visited[index] = true;
+            index = self.dependents[index]
+                .iter()
+                .copied()
+                .find(|&dependent| remaining_dependents[dependent] > 0)
+                .expect("left over worker has a left over dependent; qed");
+        }
+
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DependencyGraph;
+    use crate::Error;
+
+    fn graph(workers: &[(&'static str, Vec<String>)]) -> Result<DependencyGraph, Error> {
+        DependencyGraph::new(workers.iter().map(|(name, deps)| (*name, deps.as_slice())))
+    }
+
+    fn deps(names: &[&str]) -> Vec<String> { names.iter().map(ToString::to_string).collect() }
+
+    #[test]
+    fn test_no_dependencies() {
+        let graph = graph(&[("a", deps(&[])), ("b", deps(&[])), ("c", deps(&[]))]).unwrap();
+        assert_eq!(graph.shutdown_phases(), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_shutdown_phases() {
+        let graph = graph(&[
+            ("db", deps(&[])),
+            ("consumer", deps(&["db"])),
+            ("http", deps(&["consumer", "db"])),
+            ("metrics", deps(&[])),
+        ])
+        .unwrap();
+        assert_eq!(graph.shutdown_phases(), vec![vec![2, 3], vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn test_unknown_dependency() {
+        let err = graph(&[("consumer", deps(&["db"]))]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnknownDependency { ref worker, ref dependency }
+                if worker == "consumer" && dependency == "db"
+        ));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let err = graph(&[
+            ("db", deps(&[])),
+            ("a", deps(&["b", "db"])),
+            ("b", deps(&["c"])),
+            ("c", deps(&["a"])),
+        ])
+        .unwrap_err();
+        assert!(matches!(err, Error::DependencyCycle { ref worker } if worker != "db"));
+
+        let err = graph(&[("a", deps(&["a"]))]).unwrap_err();
+        assert!(matches!(err, Error::DependencyCycle { ref worker } if worker == "a"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use tokio::{sync::watch, time::Instant};
+
+use crate::shutdown_signal::Shutdown;
+
+/// Tracks the connections of a network worker for draining them on shutdown,
+/// created with
+/// [`ShutdownSignal::drain_tracker`](crate::ShutdownSignal::drain_tracker).
+///
+/// Every accepted connection holds a [`DrainGuard`] while it is served. Once
+/// the worker is signalled, it stops accepting connections and calls
+/// [`drain`](Self::drain). Meanwhile the guards are notified with
+/// [`DrainGuard::draining`], so the connections can finish gracefully, e.g.
+/// after the request in flight.
+///
+/// The connections of all runs of a worker are counted together, the ones
+/// still in flight when the lifecycle manager is stopped are reported in
+/// [`WorkerReport::in_flight`](crate::WorkerReport::in_flight).
+#[derive(Clone, Debug)]
+pub struct DrainTracker {
+    name: String,
+    shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+/// Counts a connection as in flight until it is dropped, created with
+/// [`DrainTracker::track`].
+#[derive(Debug)]
+pub struct DrainGuard {
+    shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+impl DrainTracker {
+    pub(crate) const fn new(
+        name: String,
+        shutdown_rx: watch::Receiver<Option<Shutdown>>,
+        in_flight: Arc<watch::Sender<usize>>,
+    ) -> Self {
+        Self { name, shutdown_rx, in_flight }
+    }
+
+    /// Counts a connection as in flight until the returned guard is dropped.
+    #[must_use]
+    pub fn track(&self) -> DrainGuard {
+        self.in_flight.send_modify(|in_flight| *in_flight += 1);
+        DrainGuard { shutdown_rx: self.shutdown_rx.clone(), in_flight: self.in_flight.clone() }
+    }
+
+    /// Returns how many connections are in flight.
+    #[inline]
+    #[must_use]
+    pub fn in_flight(&self) -> usize { *self.in_flight.borrow() }
+
+    /// Waits until no connection is in flight or the deadline of the shutdown
+    /// passes, whichever comes first. Returns how many connections are still
+    /// in flight.
+    ///
+    /// The deadline is the one of
+    /// [`ShutdownSignal::deadline`](crate::ShutdownSignal::deadline), counted
+    /// from the first shutdown signal. When the group of the worker is stopped
+    /// and it has no
+    /// [shutdown timeout](crate::WorkerOptions::with_shutdown_timeout), there
+    /// is no deadline and it waits for all connections.
+    pub async fn drain(&self) -> usize {
+        let mut in_flight_rx = self.in_flight.subscribe();
+        let mut shutdown_rx = self.shutdown_rx.clone();
+        loop {
+            let deadline =
+                shutdown_rx.borrow_and_update().as_ref().and_then(|shutdown| shutdown.deadline);
+            tokio::select! {
+                _ = in_flight_rx.wait_for(|&in_flight| in_flight == 0) => return 0,
+                // e.g. the deadline is set by another shutdown signal
+                Ok(()) = shutdown_rx.changed() => {}
+                () = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() => break,
+            }
+        }
+
+        let in_flight = self.in_flight();
+        tracing::warn!(
+            "Worker {} stops draining at the deadline, {in_flight} connections are in flight",
+            self.name
+        );
+        in_flight
+    }
+}
+
+impl DrainGuard {
+    /// Returns `true` if the worker is signalled and the connection should
+    /// finish.
+    #[inline]
+    #[must_use]
+    pub fn is_draining(&self) -> bool { self.shutdown_rx.borrow().is_some() }
+
+    /// Waits until the worker is signalled and the connection should finish.
+    pub async fn draining(&self) {
+        let mut shutdown_rx = self.shutdown_rx.clone();
+        // the lifecycle manager is gone if the sender is dropped
+        let _unused = shutdown_rx.wait_for(Option::is_some).await;
+    }
+}
+
+impl Drop for DrainGuard {
+    fn drop(&mut self) { self.in_flight.send_modify(|in_flight| *in_flight -= 1); }
+}
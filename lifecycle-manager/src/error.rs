@@ -0,0 +1,74 @@
+use std::io;
+
+use snafu::Snafu;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum Error {
+    #[snafu(display("could not install signal handlers: {source}"))]
+    InstallSignalHandler { source: io::Error },
+
+    #[snafu(display("worker `{worker}` depends on unknown worker `{dependency}`"))]
+    UnknownDependency { worker: String, dependency: String },
+
+    #[snafu(display("worker `{worker}` is part of a dependency cycle"))]
+    DependencyCycle { worker: String },
+
+    #[snafu(display("could not read config {}: {source}", path.display()))]
+    LoadConfig { path: std::path::PathBuf, source: io::Error },
+
+    #[snafu(display("could not parse config {}: {source}", path.display()))]
+    ParseConfig { path: std::path::PathBuf, source: Box<dyn std::error::Error + Send + Sync> },
+
+    #[snafu(display("could not lock {}: {source}", path.display()))]
+    LockInstance { path: std::path::PathBuf, source: io::Error },
+
+    #[snafu(display("another instance holds the lock {}", path.display()))]
+    InstanceLocked { path: std::path::PathBuf },
+
+    #[snafu(display("could not spawn thread of worker `{worker}`: {source}"))]
+    SpawnThread { worker: String, source: io::Error },
+
+    #[snafu(display("could not build runtime: {source}"))]
+    BuildRuntime { source: io::Error },
+
+    #[snafu(display("could not add worker `{worker}`, lifecycle manager is shutting down"))]
+    Stopped { worker: String },
+
+    #[cfg(feature = "periodic")]
+    #[snafu(display("invalid cron expression `{expression}`: {reason}"))]
+    InvalidCronExpression { expression: String, reason: String },
+
+    #[cfg(feature = "periodic")]
+    #[snafu(display(
+        "run of worker `{worker}` is not finished in {} milliseconds",
+        max_run_duration.as_millis()
+    ))]
+    RunTimedOut { worker: String, max_run_duration: std::time::Duration },
+
+    #[cfg(feature = "health")]
+    #[snafu(display("could not bind health endpoint to {addr}: {source}"))]
+    BindHealthEndpoint { addr: std::net::SocketAddr, source: io::Error },
+
+    #[cfg(feature = "health")]
+    #[snafu(display("error occurs while serving health endpoint: {source}"))]
+    ServeHealthEndpoint { source: hyper::Error },
+
+    #[cfg(all(unix, feature = "process"))]
+    #[snafu(display("could not spawn process of worker `{worker}`: {source}"))]
+    SpawnProcess { worker: String, source: io::Error },
+
+    #[cfg(all(unix, feature = "process"))]
+    #[snafu(display("could not wait for process of worker `{worker}`: {source}"))]
+    WaitProcess { worker: String, source: io::Error },
+
+    #[cfg(all(unix, feature = "process"))]
+    #[snafu(display("process of worker `{worker}` exited with {status}"))]
+    ProcessExited { worker: String, status: std::process::ExitStatus },
+
+    #[cfg(all(unix, feature = "systemd"))]
+    #[snafu(display("could not notify systemd: {source}"))]
+    NotifySystemd { source: io::Error },
+}
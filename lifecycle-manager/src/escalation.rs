@@ -0,0 +1,126 @@
+use std::{fmt, sync::Arc};
+
+use tokio::sync::watch;
+
+use crate::shutdown_signal::Shutdown;
+
+/// Decides what happens when workers do not stop in time after another
+/// shutdown signal is received, or right away when a third one is received.
+#[derive(Clone, Default)]
+pub enum EscalationPolicy {
+    /// Abort the workers which are still running, they are reported as
+    /// [`Cancelled`](crate::WorkerOutcome::Cancelled).
+    #[default]
+    AbortWorkers,
+
+    /// Call the hook, e.g. for flushing logs before exiting the process.
+    Hook(Arc<dyn Fn() + Send + Sync>),
+
+    /// Abort the workers which are still running like
+    /// [`AbortWorkers`](Self::AbortWorkers), then exit the process with the
+    /// exit code once the report is built and the post-shutdown hooks are
+    /// called.
+    Exit(i32),
+
+    /// Do nothing and keep waiting for the workers.
+    Nothing,
+}
+
+impl EscalationPolicy {
+    pub(crate) fn escalate(&self, shutdown_tx: &watch::Sender<Option<Shutdown>>) {
+        match self {
+            Self::AbortWorkers => {
+                tracing::warn!("Abort all workers which are not stopped");
+                shutdown_tx.send_modify(|shutdown| {
+                    if let Some(shutdown) = shutdown {
+                        shutdown.escalated = true;
+                    }
+                });
+            }
+            Self::Hook(hook) => {
+                tracing::warn!("Call the escalation hook");
+                hook();
+            }
+            Self::Exit(code) => {
+                let mut signalled = false;
+                shutdown_tx.send_modify(|shutdown| {
+                    if let Some(shutdown) = shutdown {
+                        shutdown.escalated = true;
+                        shutdown.exit_code = Some(*code);
+                        signalled = true;
+                    }
+                });
+                if !signalled {
+                    // no worker is signalled yet, there is no report to wait for
+                    tracing::warn!("Force exit this process with code {code}");
+                    std::process::exit(*code);
+                }
+                tracing::warn!(
+                    "Abort all workers which are not stopped, then exit with code {code}"
+                );
+            }
+            Self::Nothing => tracing::warn!("Keep waiting for all workers to stop"),
+        }
+    }
+}
+
+impl fmt::Debug for EscalationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AbortWorkers => f.write_str("AbortWorkers"),
+            Self::Hook(_) => f.debug_tuple("Hook").finish_non_exhaustive(),
+            Self::Exit(code) => f.debug_tuple("Exit").field(code).finish(),
+            Self::Nothing => f.write_str("Nothing"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use tokio::{sync::watch, time::Instant};
+
+    use super::EscalationPolicy;
+    use crate::{shutdown_signal::Shutdown, ShutdownReason};
+
+    #[test]
+    fn test_abort_workers() {
+        let (shutdown_tx, shutdown_rx) = watch::channel(None);
+        EscalationPolicy::AbortWorkers.escalate(&shutdown_tx);
+        assert!(shutdown_rx.borrow().is_none());
+
+        shutdown_tx.send_replace(Some(Shutdown::new(ShutdownReason::Custom, Instant::now())));
+        EscalationPolicy::AbortWorkers.escalate(&shutdown_tx);
+        assert!(shutdown_rx.borrow().as_ref().is_some_and(|shutdown| shutdown.escalated));
+    }
+
+    #[test]
+    fn test_exit() {
+        let (shutdown_tx, shutdown_rx) =
+            watch::channel(Some(Shutdown::new(ShutdownReason::Custom, Instant::now())));
+        EscalationPolicy::Exit(3).escalate(&shutdown_tx);
+        assert!(shutdown_rx
+            .borrow()
+            .as_ref()
+            .is_some_and(|shutdown| shutdown.escalated && shutdown.exit_code == Some(3)));
+    }
+
+    #[test]
+    fn test_hook() {
+        let called = Arc::new(AtomicBool::new(false));
+        let policy = EscalationPolicy::Hook({
+            let called = called.clone();
+            Arc::new(move || called.store(true, Ordering::SeqCst))
+        });
+
+        let (shutdown_tx, shutdown_rx) =
+            watch::channel(Some(Shutdown::new(ShutdownReason::Custom, Instant::now())));
+        policy.escalate(&shutdown_tx);
+        assert!(called.load(Ordering::SeqCst));
+        assert!(shutdown_rx.borrow().as_ref().is_some_and(|shutdown| !shutdown.escalated));
+    }
+}
@@ -0,0 +1,117 @@
+use std::{fmt, path::PathBuf};
+
+use tokio::sync::broadcast;
+
+#[cfg(unix)]
+use crate::ReloadSignal;
+use crate::{ShutdownReason, ShutdownState};
+
+/// Something which happened in a [`LifecycleManager`](crate::LifecycleManager),
+/// received with [`LifecycleEvents`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LifecycleEvent {
+    /// The worker is spawned.
+    WorkerSpawned { worker: String },
+
+    /// The worker is started, see
+    /// [`WorkerOptions::with_started_notification`](crate::WorkerOptions::with_started_notification).
+    WorkerStarted { worker: String },
+
+    /// A run of the worker returned an error or panicked, it may be restarted
+    /// afterwards.
+    WorkerFailed { worker: String, error: String },
+
+    /// The worker is restarted for the `restarts`-th time.
+    WorkerRestarted { worker: String, restarts: u32 },
+
+    /// The failure of the worker tripped the
+    /// [`CircuitBreaker`](crate::CircuitBreaker), all workers are shut down.
+    CircuitBreakerTripped { worker: String },
+
+    /// The worker is stopped for good, with the
+    /// [outcome](crate::WorkerOutcome) it is reported with.
+    WorkerStopped { worker: String, outcome: String },
+
+    /// A shutdown signal is received.
+    SignalReceived { reason: ShutdownReason },
+
+    /// A reload signal is received.
+    #[cfg(unix)]
+    ReloadSignalReceived { signal: ReloadSignal },
+
+    /// The [`ShutdownState`] is advanced.
+    ShutdownStateChanged { state: ShutdownState },
+
+    /// The config is reloaded by a [`ConfigWorker`](crate::ConfigWorker).
+    ConfigReloaded { path: PathBuf },
+
+    /// The config could not be reloaded by a
+    /// [`ConfigWorker`](crate::ConfigWorker), the previous one is kept.
+    ConfigReloadFailed { path: PathBuf, error: String },
+}
+
+/// Receives the [`LifecycleEvent`]s of a lifecycle manager, created with
+/// [`LifecycleManager::events`](crate::LifecycleManager::events).
+///
+/// Only events sent after the receiver is created are received.
+#[derive(Debug)]
+pub struct LifecycleEvents {
+    event_rx: broadcast::Receiver<LifecycleEvent>,
+}
+
+impl LifecycleEvents {
+    pub(crate) const fn new(event_rx: broadcast::Receiver<LifecycleEvent>) -> Self {
+        Self { event_rx }
+    }
+
+    /// Waits for the next event, returns `None` once the lifecycle manager is
+    /// stopped.
+    pub async fn recv(&mut self) -> Option<LifecycleEvent> {
+        loop {
+            match self.event_rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Skipped {skipped} lifecycle events");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Clone for LifecycleEvents {
+    fn clone(&self) -> Self { Self { event_rx: self.event_rx.resubscribe() } }
+}
+
+impl fmt::Display for LifecycleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WorkerSpawned { worker } => write!(f, "worker {worker} is spawned"),
+            Self::WorkerStarted { worker } => write!(f, "worker {worker} is started"),
+            Self::WorkerFailed { worker, error } => write!(f, "worker {worker} failed: {error}"),
+            Self::WorkerRestarted { worker, restarts } => {
+                write!(f, "worker {worker} is restarted ({restarts} restarts)")
+            }
+            Self::CircuitBreakerTripped { worker } => {
+                write!(f, "worker {worker} tripped the circuit breaker")
+            }
+            Self::WorkerStopped { worker, outcome } => {
+                write!(f, "worker {worker} is stopped: {outcome}")
+            }
+            Self::SignalReceived { reason } => write!(f, "shutting down, {reason}"),
+            #[cfg(unix)]
+            Self::ReloadSignalReceived { signal } => write!(f, "received {signal}"),
+            Self::ShutdownStateChanged { state } => write!(f, "shutdown state is {state:?}"),
+            Self::ConfigReloaded { path } => write!(f, "config {} is reloaded", path.display()),
+            Self::ConfigReloadFailed { path, error } => {
+                write!(f, "config {} could not be reloaded: {error}", path.display())
+            }
+        }
+    }
+}
+
+/// Sends `event` to the subscribed [`LifecycleEvents`], if there are any.
+pub(crate) fn emit(event_tx: &broadcast::Sender<LifecycleEvent>, event: LifecycleEvent) {
+    let _unused = event_tx.send(event);
+}
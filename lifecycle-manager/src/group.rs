@@ -0,0 +1,47 @@
+use tokio::sync::{mpsc, oneshot};
+
+/// A request for stopping the workers of a group, answered once they are
+/// stopped.
+pub(crate) struct GroupStop {
+    pub group: String,
+    pub stopped_tx: oneshot::Sender<()>,
+}
+
+/// A handle for stopping the workers added with
+/// [`WorkerOptions::with_group`](crate::WorkerOptions::with_group), created by
+/// [`LifecycleManager::group`](crate::LifecycleManager::group).
+///
+/// Stopping a group signals its workers in dependency order without shutting
+/// down the lifecycle manager, the other workers keep running unless they
+/// depend on a worker of the group, those are stopped with it. Workers are
+/// aborted after their
+/// [shutdown timeout](crate::WorkerOptions::with_shutdown_timeout) as in a
+/// shutdown. A shutdown of the lifecycle manager still signals the workers of
+/// every group.
+#[derive(Clone, Debug)]
+pub struct WorkerGroup {
+    name: String,
+    stop_tx: mpsc::UnboundedSender<GroupStop>,
+}
+
+impl WorkerGroup {
+    pub(crate) fn new(name: String, stop_tx: mpsc::UnboundedSender<GroupStop>) -> Self {
+        Self { name, stop_tx }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Signals the workers of this group and waits until they are stopped.
+    ///
+    /// Workers added to the group afterwards are spawned as usual. If the
+    /// lifecycle manager is not serving yet, this waits until it is.
+    pub async fn stop(&self) {
+        let (stopped_tx, stopped_rx) = oneshot::channel();
+        if self.stop_tx.send(GroupStop { group: self.name.clone(), stopped_tx }).is_ok() {
+            // the lifecycle manager stopped all workers if the sender is dropped
+            let _unused = stopped_rx.await;
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use snafu::ensure;
+use tokio::sync::mpsc;
+
+#[cfg(feature = "periodic")]
+use crate::Schedule;
+use crate::{
+    error::{Result, StoppedSnafu, UnknownDependencySnafu},
+    group::GroupStop,
+    worker::WorkerEntry,
+    BlockingWorker, Error, ShutdownSignal, Worker, WorkerGroup, WorkerOptions,
+};
+
+/// A handle for adding workers to a
+/// [`LifecycleManager`](crate::LifecycleManager) which may already be serving.
+///
+/// Workers added before [`serve`](crate::LifecycleManager::serve) is called
+/// are spawned together with the other workers, workers added afterwards are
+/// spawned immediately. The dependencies of a worker must be added before the
+/// worker itself.
+pub struct LifecycleHandle<E> {
+    worker_tx: mpsc::UnboundedSender<WorkerEntry<E>>,
+    stop_tx: mpsc::UnboundedSender<GroupStop>,
+    worker_names: Arc<Mutex<HashSet<String>>>,
+}
+
+impl<E> Clone for LifecycleHandle<E> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            worker_tx: self.worker_tx.clone(),
+            stop_tx: self.stop_tx.clone(),
+            worker_names: self.worker_names.clone(),
+        }
+    }
+}
+
+impl<E> LifecycleHandle<E>
+where
+    E: std::error::Error + Send + 'static,
+{
+    pub(crate) fn new(
+    ) -> (Self, mpsc::UnboundedReceiver<WorkerEntry<E>>, mpsc::UnboundedReceiver<GroupStop>) {
+        let (worker_tx, worker_rx) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = mpsc::unbounded_channel();
+        (Self { worker_tx, stop_tx, worker_names: Arc::default() }, worker_rx, stop_rx)
+    }
+
+    /// Returns a handle for stopping the workers added to the group named
+    /// `name`.
+    #[inline]
+    #[must_use]
+    pub fn group(&self, name: impl Into<String>) -> WorkerGroup {
+        WorkerGroup::new(name.into(), self.stop_tx.clone())
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_worker(&self, worker: impl Worker<Error = E> + Send + 'static) -> Result<()> {
+        self.add_worker_with_options(worker, WorkerOptions::default())
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_worker_with_options(
+        &self,
+        worker: impl Worker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Result<()> {
+        self.send(WorkerEntry::from_worker(worker, options))
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_worker_fn(
+        &self,
+        worker_name: &str,
+        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Result<()> {
+        self.add_worker_fn_with_options(worker_name, WorkerOptions::default(), worker_fn)
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_worker_fn_with_options(
+        &self,
+        worker_name: &str,
+        options: WorkerOptions,
+        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Result<()> {
+        self.send(WorkerEntry::new_once(worker_name, options, worker_fn))
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_blocking_worker(
+        &self,
+        worker: impl BlockingWorker<Error = E> + Send + 'static,
+    ) -> Result<()>
+    where
+        E: From<Error>,
+    {
+        self.add_blocking_worker_with_options(worker, WorkerOptions::default())
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_blocking_worker_with_options(
+        &self,
+        worker: impl BlockingWorker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Result<()>
+    where
+        E: From<Error>,
+    {
+        self.send(WorkerEntry::from_blocking_worker(worker, options))
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[cfg(feature = "periodic")]
+    #[inline]
+    pub fn add_periodic_worker(
+        &self,
+        worker_name: &str,
+        schedule: Schedule,
+        job: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Result<()>
+    where
+        E: From<Error>,
+    {
+        self.add_periodic_worker_with_options(worker_name, schedule, WorkerOptions::default(), job)
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[cfg(feature = "periodic")]
+    #[inline]
+    pub fn add_periodic_worker_with_options(
+        &self,
+        worker_name: &str,
+        schedule: Schedule,
+        options: WorkerOptions,
+        job: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Result<()>
+    where
+        E: From<Error>,
+    {
+        self.send(WorkerEntry::periodic(worker_name, schedule, options, job))
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_restartable_worker<W>(
+        &self,
+        factory: impl FnMut() -> W + Send + 'static,
+        options: WorkerOptions,
+    ) -> Result<()>
+    where
+        W: Worker<Error = E> + Send + 'static,
+    {
+        self.send(WorkerEntry::from_factory(factory, options))
+    }
+
+    /// # Errors
+    ///
+    /// If a dependency of the worker is unknown, or if the lifecycle manager
+    /// is shutting down.
+    #[inline]
+    pub fn add_restartable_worker_fn(
+        &self,
+        worker_name: &str,
+        options: WorkerOptions,
+        worker_fn: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Result<()> {
+        self.send(WorkerEntry::new(worker_name, options, worker_fn))
+    }
+
+    pub(crate) fn register(&self, worker_name: &str) {
+        self.worker_names
+            .lock()
+            .expect("lock is not poisoned; qed")
+            .insert(worker_name.to_string());
+    }
+
+    fn send(&self, entry: WorkerEntry<E>) -> Result<()> {
+        let mut worker_names = self.worker_names.lock().expect("lock is not poisoned; qed");
+        for dependency in entry.options.dependencies() {
+            ensure!(
+                worker_names.contains(dependency),
+                UnknownDependencySnafu { worker: &entry.name, dependency }
+            );
+        }
+
+        let worker_name = entry.name.clone();
+        if self.worker_tx.send(entry).is_err() {
+            return StoppedSnafu { worker: worker_name }.fail();
+        }
+
+        worker_names.insert(worker_name);
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    routing::get,
+    Router,
+};
+use snafu::ResultExt;
+use tokio::sync::watch;
+
+use crate::{
+    error::{BindHealthEndpointSnafu, ServeHealthEndpointSnafu},
+    Error, PrometheusRecorder, ShutdownSignal, ShutdownState, Worker,
+};
+
+/// The path answering whether the process is alive.
+pub const LIVENESS_PATH: &str = "/healthz";
+
+/// The path answering whether the process is ready to receive traffic.
+pub const READINESS_PATH: &str = "/readyz";
+
+/// The path serving the metrics in the Prometheus text format, see
+/// [`HealthWorker::with_metrics`].
+pub const METRICS_PATH: &str = "/metrics";
+
+type Components = Arc<Mutex<BTreeMap<String, bool>>>;
+
+/// A worker serving [`LIVENESS_PATH`] and [`READINESS_PATH`] over HTTP,
+/// created by
+/// [`LifecycleManager::health_worker`](crate::LifecycleManager::health_worker).
+///
+/// The process is live as long as this worker is serving. It is ready while
+/// no shutdown signal is received and every [`ReadinessHandle`] reports
+/// ready. Let other workers [depend on](crate::WorkerOptions::depends_on)
+/// this worker to keep liveness served while they drain.
+pub struct HealthWorker<E> {
+    listener: TcpListener,
+    shutdown_state: watch::Receiver<ShutdownState>,
+    components: Components,
+    metrics: Option<PrometheusRecorder>,
+    _error: PhantomData<fn() -> E>,
+}
+
+impl<E> HealthWorker<E> {
+    pub(crate) fn bind(
+        addr: SocketAddr,
+        shutdown_state: watch::Receiver<ShutdownState>,
+    ) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).context(BindHealthEndpointSnafu { addr })?;
+        listener.set_nonblocking(true).context(BindHealthEndpointSnafu { addr })?;
+        Ok(Self {
+            listener,
+            shutdown_state,
+            components: Arc::default(),
+            metrics: None,
+            _error: PhantomData,
+        })
+    }
+
+    /// Returns the address the health endpoint is bound to.
+    ///
+    /// # Errors
+    ///
+    /// If the address of the listener could not be queried.
+    #[inline]
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> { self.listener.local_addr() }
+
+    /// Serves the metrics recorded by `recorder` on [`METRICS_PATH`], pass a
+    /// clone of the recorder given to
+    /// [`LifecycleManager::with_metrics_recorder`](crate::LifecycleManager::with_metrics_recorder).
+    #[inline]
+    #[must_use]
+    pub fn with_metrics(mut self, recorder: PrometheusRecorder) -> Self {
+        self.metrics = Some(recorder);
+        self
+    }
+
+    /// Registers a component named `name` which must report ready before the
+    /// process is ready. The component is not ready until it says so.
+    #[must_use]
+    pub fn readiness_handle(&self, name: impl Into<String>) -> ReadinessHandle {
+        let name = name.into();
+        self.components.lock().expect("lock is not poisoned; qed").insert(name.clone(), false);
+        ReadinessHandle { name, components: self.components.clone() }
+    }
+}
+
+#[async_trait]
+impl<E> Worker for HealthWorker<E>
+where
+    E: From<Error> + Send,
+{
+    type Error = E;
+
+    fn name(&self) -> &str { "health-endpoint" }
+
+    async fn serve(self, shutdown_signal: ShutdownSignal) -> Result<(), Self::Error> {
+        let Self { listener, shutdown_state, components, metrics, .. } = self;
+
+        let mut router = Router::new()
+            .route(LIVENESS_PATH, get(|| async { StatusCode::OK }))
+            .route(READINESS_PATH, get(readiness))
+            .with_state((shutdown_state, components));
+        if let Some(metrics) = metrics {
+            router = router.route(
+                METRICS_PATH,
+                get(move || async move {
+                    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+                }),
+            );
+        }
+
+        axum::Server::from_tcp(listener)
+            .context(ServeHealthEndpointSnafu)?
+            .serve(router.into_make_service())
+            .with_graceful_shutdown(async {
+                shutdown_signal.await;
+            })
+            .await
+            .context(ServeHealthEndpointSnafu)?;
+
+        Ok(())
+    }
+}
+
+async fn readiness(
+    State((shutdown_state, components)): State<(watch::Receiver<ShutdownState>, Components)>,
+) -> (StatusCode, String) {
+    let state = *shutdown_state.borrow();
+    if state != ShutdownState::WaitForSignal {
+        return (StatusCode::SERVICE_UNAVAILABLE, format!("shutdown state is {state:?}\n"));
+    }
+
+    let not_ready = components
+        .lock()
+        .expect("lock is not poisoned; qed")
+        .iter()
+        .filter(|(_, ready)| !**ready)
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    if not_ready.is_empty() {
+        (StatusCode::OK, "ready\n".to_string())
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, format!("not ready: {}\n", not_ready.join(", ")))
+    }
+}
+
+/// A handle for a worker to report its readiness to a [`HealthWorker`].
+#[derive(Clone)]
+pub struct ReadinessHandle {
+    name: String,
+    components: Components,
+}
+
+impl ReadinessHandle {
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str { &self.name }
+
+    #[inline]
+    pub fn set_ready(&self, ready: bool) {
+        if let Some(component) =
+            self.components.lock().expect("lock is not poisoned; qed").get_mut(&self.name)
+        {
+            *component = ready;
+        }
+    }
+}
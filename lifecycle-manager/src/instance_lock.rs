@@ -0,0 +1,148 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use snafu::ResultExt;
+use tokio::sync::watch;
+
+use crate::{
+    error::{InstanceLockedSnafu, LockInstanceSnafu, Result},
+    shutdown_signal::Shutdown,
+};
+
+/// An exclusive lock on a file, which a
+/// [`LifecycleManager`](crate::LifecycleManager) takes before spawning its
+/// workers so that only one instance on a host runs them, set with
+/// [`with_instance_lock`](crate::LifecycleManager::with_instance_lock).
+///
+/// The lock is an advisory `flock`, it only excludes other processes taking
+/// it. The file is created if it does not exist and is never removed. Instance
+/// locks are only supported on unix, elsewhere serving fails with
+/// [`Error::LockInstance`](crate::Error::LockInstance). It is released once all
+/// workers are stopped, or by the operating system if the process dies.
+///
+/// If another process holds the lock, serving fails with
+/// [`Error::InstanceLocked`](crate::Error::InstanceLocked) unless
+/// [`with_wait`](Self::with_wait) is set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstanceLock {
+    path: PathBuf,
+    retry_interval: Option<Duration>,
+}
+
+/// The lock held while the workers are running, released when it is dropped.
+#[derive(Debug)]
+pub(crate) struct InstanceLockGuard {
+    path: PathBuf,
+    file: File,
+}
+
+impl InstanceLock {
+    #[inline]
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), retry_interval: None }
+    }
+
+    /// Waits for the lock held by another process instead of failing, trying
+    /// to take it every `retry_interval`. Shutdown signals received meanwhile
+    /// stop waiting, no worker is started then.
+    #[inline]
+    #[must_use]
+    pub fn with_wait(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = Some(retry_interval);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Takes the lock, returns `None` if the lifecycle manager is shut down
+    /// while waiting for it.
+    pub(crate) async fn acquire(
+        &self,
+        mut shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    ) -> Result<Option<InstanceLockGuard>> {
+        let path = &self.path;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .context(LockInstanceSnafu { path })?;
+
+        let mut logged = false;
+        loop {
+            if try_lock(&file).context(LockInstanceSnafu { path })? {
+                tracing::info!("Instance lock {} is acquired", path.display());
+                return Ok(Some(InstanceLockGuard { path: path.clone(), file }));
+            }
+
+            let Some(retry_interval) = self.retry_interval else {
+                return InstanceLockedSnafu { path }.fail();
+            };
+            if !logged {
+                tracing::info!(
+                    "Instance lock {} is held by another process, wait for it",
+                    path.display()
+                );
+                logged = true;
+            }
+            tokio::select! {
+                () = tokio::time::sleep(retry_interval) => {}
+                _ = shutdown_rx.wait_for(Option::is_some) => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Drop for InstanceLockGuard {
+    fn drop(&mut self) {
+        // closing the file releases the lock anyway
+        if let Err(err) = unlock(&self.file) {
+            tracing::warn!("Failed to release instance lock {}, error: {err}", self.path.display());
+        } else {
+            tracing::info!("Instance lock {} is released", self.path.display());
+        }
+    }
+}
+
+/// Takes an exclusive lock on `file`, returns `false` if another process holds
+/// it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: `flock` does not access memory of this process
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::WouldBlock {
+            return Ok(false);
+        }
+        return Err(err);
+    }
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> io::Result<bool> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "instance locks are only supported on unix"))
+}
+
+#[cfg(unix)]
+fn unlock(file: &File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: `flock` does not access memory of this process
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn unlock(_file: &File) -> io::Result<()> { Ok(()) }
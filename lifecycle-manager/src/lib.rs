@@ -1,40 +1,119 @@
+mod blocking;
+mod circuit_breaker;
+mod config;
+mod dependency_graph;
+mod drain;
+mod error;
+mod escalation;
+mod events;
+mod group;
+mod handle;
+#[cfg(feature = "health")]
+mod health;
+mod instance_lock;
+mod metrics;
+#[cfg(feature = "periodic")]
+mod periodic;
+#[cfg(all(unix, feature = "process"))]
+mod process;
+#[cfg(unix)]
+mod reload;
+mod report;
+mod restart;
+mod runtime;
+mod shutdown_signal;
 mod shutdown_state;
 mod signal_watcher;
+mod supervisor;
+#[cfg(all(unix, feature = "systemd"))]
+mod systemd;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
 #[cfg(windows)]
 mod windows;
 mod worker;
 
-use std::{future::Future, pin::Pin, time::Duration};
+use std::{future::Future, time::Duration};
 
 use futures::future::BoxFuture;
-use tokio::task::JoinHandle;
+use snafu::ResultExt;
+use tokio::sync::{mpsc, watch};
 
-use self::shutdown_state::ShutdownState;
+#[cfg(feature = "health")]
+pub use self::health::{
+    HealthWorker, ReadinessHandle, LIVENESS_PATH, METRICS_PATH, READINESS_PATH,
+};
+#[cfg(feature = "periodic")]
+pub use self::periodic::Schedule;
+#[cfg(all(unix, feature = "process"))]
+pub use self::process::{ProcessWorker, StopSignal};
+#[cfg(unix)]
+pub use self::reload::{ReloadReceiver, ReloadSignal};
+#[cfg(all(unix, feature = "systemd"))]
+pub use self::systemd::{SystemdNotifier, WatchdogHandle};
 pub use self::{
+    blocking::{BlockingWorker, ShutdownToken},
+    circuit_breaker::CircuitBreaker,
+    config::ConfigWorker,
+    drain::{DrainGuard, DrainTracker},
+    error::Error,
+    escalation::EscalationPolicy,
+    events::{LifecycleEvent, LifecycleEvents},
+    group::WorkerGroup,
+    handle::LifecycleHandle,
+    instance_lock::InstanceLock,
+    metrics::{MetricsRecorder, PrometheusRecorder},
+    report::{LifecycleReport, WorkerOutcome, WorkerReport},
+    restart::RestartPolicy,
+    runtime::RuntimeOptions,
+    shutdown_signal::{ShutdownReason, ShutdownSignal, Signal},
+    shutdown_state::ShutdownState,
     signal_watcher::{Builder as SignalWatcherBuilder, SignalWatcher},
-    worker::Worker,
+    supervisor::FailurePolicy,
+    worker::{Worker, WorkerOptions},
+};
+use self::{
+    dependency_graph::DependencyGraph,
+    error::{BuildRuntimeSnafu, InstallSignalHandlerSnafu, Result},
+    group::GroupStop,
+    supervisor::{Reporter, Supervisor},
+    worker::WorkerEntry,
 };
 
-pub type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>;
-
-// developing notes
-// this should be refactor to include following features:
-// 1. worker can still be added after serve
-// 2. worker should known what kind of signal
-// 3. worker should be shutdown orderly instead of kill all and wait for
-// timeout
 pub struct LifecycleManager<E> {
     signal_watcher_builder: SignalWatcherBuilder,
-    join_handles: Vec<(String, JoinHandle<Result<(), E>>)>,
+    workers: Vec<WorkerEntry<E>>,
+    handle: LifecycleHandle<E>,
+    worker_rx: mpsc::UnboundedReceiver<WorkerEntry<E>>,
+    stop_rx: mpsc::UnboundedReceiver<GroupStop>,
+    failure_policy: FailurePolicy,
+    circuit_breaker: Option<CircuitBreaker>,
+    instance_lock: Option<InstanceLock>,
+    workers_started: watch::Sender<bool>,
+    post_shutdown_hooks: Vec<PostShutdownHook<E>>,
 }
 
+type PostShutdownHook<E> = Box<dyn FnOnce(&LifecycleReport<E>) + Send>;
+
 impl<E> Default for LifecycleManager<E>
 where
     E: std::error::Error + Send + 'static,
 {
     #[inline]
     fn default() -> Self {
-        Self { signal_watcher_builder: SignalWatcher::builder(), join_handles: Vec::new() }
+        let (handle, worker_rx, stop_rx) = LifecycleHandle::new();
+        Self {
+            signal_watcher_builder: SignalWatcher::builder(),
+            workers: Vec::new(),
+            handle,
+            worker_rx,
+            stop_rx,
+            failure_policy: FailurePolicy::default(),
+            circuit_breaker: None,
+            instance_lock: None,
+            workers_started: watch::channel(false).0,
+            post_shutdown_hooks: Vec::new(),
+        }
     }
 }
 
@@ -46,6 +125,10 @@ where
     #[must_use]
     pub fn new() -> Self { Self::default() }
 
+    /// Sets how long workers have to stop after they are signalled, 10
+    /// seconds by default. Workers see the deadline with
+    /// [`ShutdownSignal::deadline`], another shutdown signal escalates once it
+    /// passes, see [`EscalationPolicy`].
     #[inline]
     #[must_use]
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
@@ -53,66 +136,513 @@ where
         self
     }
 
+    /// Keeps workers serving for `delay` after the first shutdown signal,
+    /// while the [`ShutdownState`] already reports the shutdown, e.g. for
+    /// Kubernetes to remove the pod from endpoints. Another shutdown signal
+    /// skips the delay. The delay counts against the
+    /// [timeout](Self::with_timeout) and the shutdown timeouts of workers,
+    /// which are measured from the first shutdown signal.
+    #[inline]
+    #[must_use]
+    pub fn with_pre_shutdown_delay(mut self, delay: Duration) -> Self {
+        self.signal_watcher_builder.with_pre_shutdown_delay(delay);
+        self
+    }
+
+    /// Sets what happens when workers do not stop in time after another
+    /// shutdown signal is received, see [`EscalationPolicy`].
+    #[inline]
+    #[must_use]
+    pub fn with_escalation_policy(mut self, escalation_policy: EscalationPolicy) -> Self {
+        self.signal_watcher_builder.with_escalation_policy(escalation_policy);
+        self
+    }
+
     #[inline]
     #[must_use]
-    pub fn with_custom_shutdown(mut self, shutdown_signal: ShutdownSignal) -> Self {
+    pub fn with_custom_shutdown(
+        mut self,
+        shutdown_signal: impl Future<Output = ()> + Send + 'static,
+    ) -> Self {
         self.signal_watcher_builder.with_custom_shutdown(shutdown_signal);
         self
     }
 
+    /// Listens for `signal` and sends it to the [`ReloadReceiver`]s instead of
+    /// shutting down. Receiving a reload signal never advances the shutdown.
+    #[cfg(unix)]
     #[inline]
     #[must_use]
-    pub fn add_worker(mut self, worker: impl Worker<Error = E> + 'static) -> Self {
-        let worker_name = worker.name().to_string();
-        let signal = self.signal_watcher_builder.create_shutdown_signal(&worker_name);
-        let fut = worker.serve(signal);
-        let join_handle = tokio::spawn(fut);
-        self.join_handles.push((worker_name, join_handle));
+    pub fn with_reload_signal(mut self, signal: ReloadSignal) -> Self {
+        self.signal_watcher_builder.with_reload_signal(signal);
         self
     }
 
+    /// Returns a receiver for the signals registered with
+    /// [`with_reload_signal`](Self::with_reload_signal), which can be moved
+    /// into workers.
+    #[cfg(unix)]
     #[inline]
     #[must_use]
-    pub fn add_worker_fn(
+    pub fn reload_receiver(&self) -> ReloadReceiver {
+        self.signal_watcher_builder.reload_receiver()
+    }
+
+    /// Calls `hook` right before the shutdown signal is sent to workers, e.g.
+    /// for deregistering from service discovery. Hooks are called in the
+    /// order they are added and must not block for long.
+    #[inline]
+    #[must_use]
+    pub fn with_pre_shutdown_hook(
         mut self,
+        hook: impl Fn(&ShutdownReason) + Send + 'static,
+    ) -> Self {
+        self.signal_watcher_builder.with_pre_shutdown_hook(hook);
+        self
+    }
+
+    /// Calls `hook` with the report after all workers are stopped, e.g. for
+    /// flushing telemetry exporters. Hooks are called in the order they are
+    /// added.
+    #[inline]
+    #[must_use]
+    pub fn with_post_shutdown_hook(
+        mut self,
+        hook: impl FnOnce(&LifecycleReport<E>) + Send + 'static,
+    ) -> Self {
+        self.post_shutdown_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Records the metrics of the workers and the [`ShutdownState`] with
+    /// `recorder`, e.g. a [`PrometheusRecorder`].
+    #[inline]
+    #[must_use]
+    pub fn with_metrics_recorder(mut self, recorder: impl MetricsRecorder + 'static) -> Self {
+        self.signal_watcher_builder.with_metrics_recorder(recorder);
+        self
+    }
+
+    /// Receives signals from `signal_source` instead of the operating system,
+    /// see [`testing`].
+    #[cfg(any(test, feature = "test-util"))]
+    #[inline]
+    #[must_use]
+    pub fn with_signal_source(mut self, signal_source: testing::SignalSource) -> Self {
+        self.signal_watcher_builder.with_signal_source(signal_source);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_failure_policy(mut self, failure_policy: FailurePolicy) -> Self {
+        self.failure_policy = failure_policy;
+        self
+    }
+
+    /// Shuts down all workers gracefully when the `circuit_breaker` is
+    /// tripped, e.g. when restartable workers keep crashing.
+    #[inline]
+    #[must_use]
+    pub const fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Takes the `instance_lock` before spawning any worker, so that only one
+    /// process on the host runs the workers. The lock is released once all
+    /// workers are stopped.
+    #[inline]
+    #[must_use]
+    pub fn with_instance_lock(mut self, instance_lock: InstanceLock) -> Self {
+        self.instance_lock = Some(instance_lock);
+        self
+    }
+
+    /// Returns a receiver of the [`ShutdownState`], which leaves
+    /// [`WaitForSignal`](ShutdownState::WaitForSignal) as soon as the first
+    /// shutdown signal is received.
+    #[inline]
+    #[must_use]
+    pub fn shutdown_state(&self) -> watch::Receiver<ShutdownState> {
+        self.signal_watcher_builder.shutdown_state()
+    }
+
+    /// Returns a receiver telling whether all workers are started, see
+    /// [`WorkerOptions::with_started_notification`]. It turns `false` again
+    /// while workers added at runtime are starting.
+    #[inline]
+    #[must_use]
+    pub fn workers_started(&self) -> watch::Receiver<bool> { self.workers_started.subscribe() }
+
+    /// Returns a receiver for the [`LifecycleEvent`]s of this lifecycle
+    /// manager, subscribe before [`serve`](Self::serve) is called to receive
+    /// all of them.
+    #[inline]
+    #[must_use]
+    pub fn events(&self) -> LifecycleEvents { self.signal_watcher_builder.events() }
+
+    /// Creates a worker reloading the config file at `path` with `parser`,
+    /// which is added like any other worker. The config is also reloaded on
+    /// the signals registered with
+    /// [`with_reload_signal`](Self::with_reload_signal), which this does not
+    /// do, e.g. register [`ReloadSignal::Hangup`] for reloading on `SIGHUP`.
+    ///
+    /// The worker is named `config-worker:` followed by `path` unless it is
+    /// renamed with [`ConfigWorker::with_name`]. The file is read once here,
+    /// on the calling thread.
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be read or parsed.
+    pub fn config_worker<C, F, PE>(
+        &self,
+        path: impl Into<std::path::PathBuf>,
+        parser: F,
+    ) -> Result<ConfigWorker<C, E>>
+    where
+        C: Send + Sync + 'static,
+        F: Fn(&str) -> std::result::Result<C, PE> + Send + Sync + 'static,
+        PE: std::error::Error + Send + Sync + 'static,
+    {
+        ConfigWorker::load(
+            path.into(),
+            parser,
+            #[cfg(unix)]
+            self.reload_receiver(),
+            self.signal_watcher_builder.event_sender(),
+        )
+    }
+
+    /// Creates a worker serving liveness and readiness on `addr`, which is
+    /// added like any other worker.
+    ///
+    /// # Errors
+    ///
+    /// If `addr` could not be bound.
+    #[cfg(feature = "health")]
+    #[inline]
+    pub fn health_worker(&self, addr: std::net::SocketAddr) -> Result<HealthWorker<E>> {
+        HealthWorker::bind(addr, self.shutdown_state())
+    }
+
+    /// Creates a worker notifying systemd through `$NOTIFY_SOCKET`, which is
+    /// added like any other worker. Returns `None` if this process is not
+    /// started by systemd with `Type=notify`.
+    #[cfg(all(unix, feature = "systemd"))]
+    #[inline]
+    #[must_use]
+    pub fn systemd_notifier(&self) -> Option<SystemdNotifier<E>> {
+        SystemdNotifier::from_env(self.shutdown_state(), self.workers_started())
+    }
+
+    /// Returns a handle for adding workers to this lifecycle manager, also
+    /// after [`serve`](Self::serve) is called.
+    #[inline]
+    #[must_use]
+    pub fn handle(&self) -> LifecycleHandle<E> { self.handle.clone() }
+
+    /// Returns a handle for stopping the workers added to the group named
+    /// `name` with [`WorkerOptions::with_group`].
+    #[inline]
+    #[must_use]
+    pub fn group(&self, name: impl Into<String>) -> WorkerGroup { self.handle.group(name) }
+
+    #[inline]
+    #[must_use]
+    pub fn add_worker(self, worker: impl Worker<Error = E> + Send + 'static) -> Self {
+        self.add_worker_with_options(worker, WorkerOptions::default())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn add_worker_with_options(
+        self,
+        worker: impl Worker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self {
+        self.push_worker(WorkerEntry::from_worker(worker, options))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn add_worker_fn(
+        self,
+        worker_name: &str,
+        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self {
+        self.add_worker_fn_with_options(worker_name, WorkerOptions::default(), worker_fn)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn add_worker_fn_with_options(
+        self,
+        worker_name: &str,
+        options: WorkerOptions,
+        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self {
+        self.push_worker(WorkerEntry::new_once(worker_name, options, worker_fn))
+    }
+
+    /// Adds a worker which runs on its own OS thread, see [`BlockingWorker`].
+    #[inline]
+    #[must_use]
+    pub fn add_blocking_worker(
+        self,
+        worker: impl BlockingWorker<Error = E> + Send + 'static,
+    ) -> Self
+    where
+        E: From<Error>,
+    {
+        self.add_blocking_worker_with_options(worker, WorkerOptions::default())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn add_blocking_worker_with_options(
+        self,
+        worker: impl BlockingWorker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self
+    where
+        E: From<Error>,
+    {
+        self.push_worker(WorkerEntry::from_blocking_worker(worker, options))
+    }
+
+    /// Adds a worker which calls `job` according to `schedule` until it is
+    /// signalled. Each run gets a shutdown signal of its own, runs in flight
+    /// are waited for when shutting down.
+    ///
+    /// An error of a run stops the worker once the other runs in flight are
+    /// signalled and stopped, use a
+    /// [`RestartPolicy`](WorkerOptions::with_restart_policy) for keeping it
+    /// running.
+    #[cfg(feature = "periodic")]
+    #[inline]
+    #[must_use]
+    pub fn add_periodic_worker(
+        self,
+        worker_name: &str,
+        schedule: Schedule,
+        job: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self
+    where
+        E: From<Error>,
+    {
+        self.add_periodic_worker_with_options(worker_name, schedule, WorkerOptions::default(), job)
+    }
+
+    #[cfg(feature = "periodic")]
+    #[inline]
+    #[must_use]
+    pub fn add_periodic_worker_with_options(
+        self,
+        worker_name: &str,
+        schedule: Schedule,
+        options: WorkerOptions,
+        job: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self
+    where
+        E: From<Error>,
+    {
+        self.push_worker(WorkerEntry::periodic(worker_name, schedule, options, job))
+    }
+
+    /// Adds a worker created by `factory`, which is called again whenever
+    /// the worker is restarted according to its
+    /// [`RestartPolicy`](WorkerOptions::with_restart_policy).
+    #[inline]
+    #[must_use]
+    pub fn add_restartable_worker<W>(
+        self,
+        factory: impl FnMut() -> W + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self
+    where
+        W: Worker<Error = E> + Send + 'static,
+    {
+        self.push_worker(WorkerEntry::from_factory(factory, options))
+    }
+
+    /// Adds a worker function, which is called again whenever the worker is
+    /// restarted according to its
+    /// [`RestartPolicy`](WorkerOptions::with_restart_policy).
+    #[inline]
+    #[must_use]
+    pub fn add_restartable_worker_fn(
+        self,
         worker_name: &str,
-        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>>,
+        options: WorkerOptions,
+        worker_fn: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
     ) -> Self {
-        let signal = self.signal_watcher_builder.create_shutdown_signal(worker_name);
-        let fut = worker_fn(signal);
-        let join_handle = tokio::spawn(fut);
-        self.join_handles.push((worker_name.to_string(), join_handle));
+        self.push_worker(WorkerEntry::new(worker_name, options, worker_fn))
+    }
+
+    fn push_worker(mut self, entry: WorkerEntry<E>) -> Self {
+        self.handle.register(&entry.name);
+        self.workers.push(entry);
         self
     }
 
+    /// Spawns all workers and waits until they are stopped.
+    ///
+    /// A worker is only spawned after all workers it depends on are started,
+    /// see [`WorkerOptions::with_started_notification`]. If a dependency
+    /// stops before it is started, all workers are shut down with
+    /// [`ShutdownReason::StartupFailed`].
+    ///
+    /// Once a shutdown signal is received, workers are signalled in phases:
+    /// a worker is only signalled after all workers depending on it have
+    /// stopped.
+    ///
+    /// As long as a [`LifecycleHandle`] of this lifecycle manager exists, this
+    /// keeps waiting for a shutdown signal even if all workers are stopped.
+    ///
+    /// The returned [`LifecycleReport`] tells how each worker stopped, use
+    /// [`LifecycleReport::is_success`] to decide the exit code of the
+    /// process.
+    ///
     /// # Errors
     ///
-    /// TODO: document
-    pub async fn serve(self) -> Result<(), E> {
-        // TODO: this error should be handle
-        let signal_watcher = self.signal_watcher_builder.build().expect("TODO");
-
-        for (worker_name, join_handle) in self.join_handles {
-            match join_handle.await {
-                Ok(Err(worker_error)) => {
-                    tracing::warn!(
-                        "Error occurs while worker {worker_name} is joined, error: {worker_error}"
-                    );
-                }
-                Ok(_) => (),
-                Err(join_error) => {
-                    tracing::warn!(
-                        "Error occurs while trying to join worker {worker_name}, error: \
-                         {join_error}"
-                    );
+    /// If the dependencies between workers are not valid or if the signal
+    /// handlers could not be installed.
+    pub async fn serve(self) -> Result<LifecycleReport<E>> {
+        let Self {
+            signal_watcher_builder,
+            mut workers,
+            handle,
+            mut worker_rx,
+            stop_rx,
+            failure_policy,
+            circuit_breaker,
+            instance_lock,
+            workers_started,
+            post_shutdown_hooks,
+        } = self;
+        drop(handle);
+
+        // workers added through a handle before serving are spawned with the others
+        while let Ok(entry) = worker_rx.try_recv() {
+            workers.push(entry);
+        }
+
+        // dependencies of workers added at runtime are checked by the handle
+        DependencyGraph::new(
+            workers.iter().map(|worker| (worker.name.as_str(), worker.options.dependencies())),
+        )?;
+
+        let shutdown_rx = signal_watcher_builder.subscribe();
+        let exit_rx = shutdown_rx.clone();
+        let shutdown_trigger = signal_watcher_builder.shutdown_trigger();
+        let event_tx = signal_watcher_builder.event_sender();
+        let metrics = signal_watcher_builder.metrics();
+        let signal_watcher = signal_watcher_builder.build().context(InstallSignalHandlerSnafu)?;
+
+        let instance_lock_guard = match &instance_lock {
+            Some(instance_lock) => match instance_lock.acquire(shutdown_rx.clone()).await {
+                Ok(guard) => guard,
+                Err(err) => {
+                    signal_watcher.wait();
+                    return Err(err);
                 }
+            },
+            None => None,
+        };
+
+        let report = if instance_lock.is_some() && instance_lock_guard.is_none() {
+            tracing::info!("Shut down before the instance lock is acquired, no worker is started");
+            let shutdown_reason =
+                shutdown_rx.borrow().as_ref().map(|shutdown| shutdown.reason.clone());
+            let workers = workers
+                .into_iter()
+                .map(|entry| WorkerReport {
+                    name: entry.name,
+                    outcome: WorkerOutcome::NotStarted,
+                    runtime: Duration::ZERO,
+                    in_flight: 0,
+                })
+                .collect();
+            LifecycleReport { workers, shutdown_reason }
+        } else {
+            let mut supervisor = Supervisor::new(
+                worker_rx,
+                stop_rx,
+                shutdown_trigger,
+                Reporter::new(event_tx, metrics, circuit_breaker, workers_started),
+                failure_policy,
+            );
+            for entry in workers {
+                supervisor.spawn(entry);
             }
-        }
+            supervisor.serve(shutdown_rx).await
+        };
 
         signal_watcher.wait();
-        tracing::info!("All workers are gracefully shutdown!");
+        drop(instance_lock_guard);
+        if report.is_success() {
+            tracing::info!("All workers are gracefully shutdown!");
+        } else {
+            tracing::warn!("Not all workers are gracefully shutdown");
+        }
 
-        Ok(())
+        for hook in post_shutdown_hooks {
+            hook(&report);
+        }
+
+        let exit_code = exit_rx.borrow().as_ref().and_then(|shutdown| shutdown.exit_code);
+        if let Some(code) = exit_code {
+            tracing::warn!("Force exit this process with code {code}");
+            std::process::exit(code);
+        }
+
+        Ok(report)
+    }
+
+    /// Builds a multi-threaded runtime and [serves](Self::serve) on it until
+    /// all workers are stopped, see
+    /// [`run_blocking_with_options`](Self::run_blocking_with_options).
+    ///
+    /// # Errors
+    ///
+    /// If the runtime could not be built or [`serve`](Self::serve) fails.
+    ///
+    /// # Panics
+    ///
+    /// If called within a runtime.
+    #[inline]
+    pub fn run_blocking(self) -> Result<LifecycleReport<E>> {
+        self.run_blocking_with_options(RuntimeOptions::default())
+    }
+
+    /// Builds a runtime with `options` and [serves](Self::serve) on it until
+    /// all workers are stopped. Afterwards the runtime is shut down, tasks
+    /// which are left are waited for at most the
+    /// [shutdown timeout](RuntimeOptions::with_shutdown_timeout), so that they
+    /// cannot keep the process from exiting.
+    ///
+    /// The result can be returned from `main`, which then exits with a
+    /// failure if not every worker stopped without error.
+    ///
+    /// No tracing subscriber is installed, the caller installs one before,
+    /// e.g. with `tracing_subscriber::fmt::init()`, otherwise the logs of the
+    /// lifecycle manager are discarded.
+    ///
+    /// # Errors
+    ///
+    /// If the runtime could not be built or [`serve`](Self::serve) fails.
+    ///
+    /// # Panics
+    ///
+    /// If called within a runtime.
+    pub fn run_blocking_with_options(self, options: RuntimeOptions) -> Result<LifecycleReport<E>> {
+        let runtime = options.build().context(BuildRuntimeSnafu)?;
+        let result = runtime.block_on(self.serve());
+
+        tracing::info!("Shut down the runtime");
+        runtime.shutdown_timeout(options.shutdown_timeout());
+        result
     }
 }
 
@@ -133,6 +663,10 @@ mod tests {
 
     use std::{
         net::{Ipv4Addr, SocketAddr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
         time::Duration,
     };
 
@@ -140,11 +674,27 @@ mod tests {
     use portpicker::pick_unused_port;
     use snafu::Snafu;
 
-    use super::{LifecycleManager, ShutdownSignal, Worker};
+    #[cfg(all(unix, feature = "process"))]
+    use super::ProcessWorker;
+    #[cfg(unix)]
+    use super::ReloadSignal;
+    #[cfg(feature = "periodic")]
+    use super::Schedule;
+    use super::{
+        BlockingWorker, CircuitBreaker, FailurePolicy, InstanceLock, LifecycleEvent,
+        LifecycleManager, PrometheusRecorder, RestartPolicy, RuntimeOptions, ShutdownReason,
+        ShutdownSignal, ShutdownState, ShutdownToken, Signal, Worker, WorkerOptions, WorkerOutcome,
+    };
+    use crate::testing::TestHarness;
 
     #[derive(Debug, Snafu)]
     enum Error {
         Dummy,
+
+        #[snafu(context(false))]
+        LifecycleManager {
+            source: super::Error,
+        },
     }
 
     struct DummyWorker {
@@ -191,7 +741,9 @@ mod tests {
 
             let server = axum::Server::bind(&SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port))
                 .serve(router.into_make_service())
-                .with_graceful_shutdown(shutdown_signal);
+                .with_graceful_shutdown(async {
+                    shutdown_signal.await;
+                });
 
             if let Err(err) = server.await {
                 eprintln!("Error occurs while awaiting for AxumServer {err}");
@@ -203,45 +755,54 @@ mod tests {
         }
     }
 
-    fn spawn_killer_task() -> ShutdownSignal {
-        Box::pin(async {
-            let timeout = Duration::from_secs(2);
-            println!("Killer task: sleep for {} milliseconds", timeout.as_millis());
-            tokio::time::sleep(timeout).await;
-            println!("Killer task: send shutdown signal");
-        })
+    struct OrderedWorker {
+        name: &'static str,
+        fail: bool,
+        events: Arc<Mutex<Vec<String>>>,
     }
 
-    #[cfg(unix)]
-    fn spawn_killer_thread() {
-        let pid = std::process::id();
-        let sig = libc::SIGTERM;
-
-        std::thread::spawn(move || {
-            std::thread::sleep(Duration::from_secs(2));
+    #[async_trait]
+    impl Worker for OrderedWorker {
+        type Error = Error;
 
-            println!("Send signal {sig} to current process (pid: {pid})");
+        fn name(&self) -> &str { self.name }
 
-            unsafe {
-                let pid = libc::pid_t::try_from(pid).expect("pid not overflow; qed");
-                libc::kill(pid, sig);
+        async fn serve(self, shutdown_signal: ShutdownSignal) -> Result<(), Self::Error> {
+            if self.fail {
+                self.events.lock().unwrap().push(format!("{} failed", self.name));
+                return DummySnafu.fail();
             }
-        });
+
+            shutdown_signal.await;
+            self.events.lock().unwrap().push(format!("{} signalled", self.name));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            self.events.lock().unwrap().push(format!("{} stopped", self.name));
+            Ok(())
+        }
     }
 
-    #[tokio::test]
+    async fn spawn_killer_task() {
+        let timeout = Duration::from_secs(2);
+        println!("Killer task: sleep for {} milliseconds", timeout.as_millis());
+        tokio::time::sleep(timeout).await;
+        println!("Killer task: send shutdown signal");
+    }
+
+    #[tokio::test(start_paused = true)]
     #[cfg_attr(miri, ignore)]
     async fn test_empty() -> Result<(), Error> {
         let shutdown_signal = spawn_killer_task();
-        LifecycleManager::<Error>::new().with_custom_shutdown(shutdown_signal).serve().await?;
+        let report =
+            LifecycleManager::<Error>::new().with_custom_shutdown(shutdown_signal).serve().await?;
+        assert!(report.is_success());
         Ok(())
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     #[cfg_attr(miri, ignore)]
     async fn test_with_dummy_workers() -> Result<(), Error> {
         let shutdown_signal = spawn_killer_task();
-        LifecycleManager::new()
+        let report = LifecycleManager::new()
             .with_custom_shutdown(shutdown_signal)
             .add_worker(DummyWorker::new(0))
             .add_worker(DummyWorker::new(1))
@@ -256,48 +817,1454 @@ mod tests {
             })
             .serve()
             .await?;
+        assert!(report.is_success());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_shutdown_order() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name, fail| OrderedWorker { name, fail, events: events.clone() };
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(spawn_killer_task())
+            .add_worker_with_options(
+                worker("http", false),
+                WorkerOptions::new().depends_on("consumer"),
+            )
+            .add_worker_with_options(
+                worker("consumer", false),
+                WorkerOptions::new().depends_on("db"),
+            )
+            .add_worker_with_options(worker("cache", true), WorkerOptions::new().depends_on("db"))
+            .add_worker(worker("db", false))
+            .serve()
+            .await?;
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            [
+                "cache failed",
+                "http signalled",
+                "http stopped",
+                "consumer signalled",
+                "consumer stopped",
+                "db signalled",
+                "db stopped",
+            ]
+        );
+
+        let failures: Vec<_> = report.failures().map(|worker| worker.name.as_str()).collect();
+        assert_eq!(failures, ["cache"]);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_invalid_dependencies() {
+        let result = LifecycleManager::new()
+            .with_custom_shutdown(spawn_killer_task())
+            .add_worker_fn_with_options(
+                "worker-function",
+                WorkerOptions::new().depends_on("unknown"),
+                |shutdown_signal| {
+                    Box::pin(async move {
+                        shutdown_signal.await;
+                        Ok::<_, Error>(())
+                    })
+                },
+            )
+            .serve()
+            .await;
+        assert!(matches!(result, Err(super::Error::UnknownDependency { .. })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_add_worker_with_handle() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name| OrderedWorker { name, fail: false, events: events.clone() };
+
+        let lifecycle_manager = LifecycleManager::new()
+            .with_custom_shutdown(spawn_killer_task())
+            .add_worker(worker("db"));
+        let handle = lifecycle_manager.handle();
+        handle
+            .add_worker_with_options(worker("consumer"), WorkerOptions::new().depends_on("db"))?;
+
+        let join_handle = tokio::spawn(lifecycle_manager.serve());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle
+            .add_worker_with_options(worker("http"), WorkerOptions::new().depends_on("consumer"))?;
+        assert!(matches!(
+            handle.add_worker_with_options(
+                worker("tenant"),
+                WorkerOptions::new().depends_on("unknown")
+            ),
+            Err(super::Error::UnknownDependency { .. })
+        ));
+
+        let report = join_handle.await.unwrap()?;
+        let workers: Vec<_> = report.workers.iter().map(|worker| worker.name.as_str()).collect();
+        assert_eq!(workers, ["db", "consumer", "http"]);
+        assert!(report.is_success());
+        assert!(matches!(handle.add_worker(worker("late")), Err(super::Error::Stopped { .. })));
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            [
+                "http signalled",
+                "http stopped",
+                "consumer signalled",
+                "consumer stopped",
+                "db signalled",
+                "db stopped",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_restart_worker() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(spawn_killer_task())
+            .add_restartable_worker_fn(
+                "flaky-worker",
+                WorkerOptions::new().with_restart_policy(
+                    RestartPolicy::exponential_backoff(
+                        Duration::from_millis(10),
+                        Duration::from_millis(100),
+                    )
+                    .with_max_restarts(3),
+                ),
+                {
+                    let runs = runs.clone();
+                    move |shutdown_signal| {
+                        let run = runs.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async move {
+                            if run < 2 {
+                                return DummySnafu.fail();
+                            }
+                            shutdown_signal.await;
+                            Ok(())
+                        })
+                    }
+                },
+            )
+            .serve()
+            .await?;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert!(report.is_success());
         Ok(())
     }
 
     #[tokio::test]
     #[cfg_attr(miri, ignore)]
-    async fn test_with_axum_server() -> Result<(), Error> {
-        let shutdown_signal = spawn_killer_task();
+    async fn test_restart_limit_exceeded() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let runs = Arc::new(AtomicUsize::new(0));
 
-        LifecycleManager::new()
-            .with_custom_shutdown(shutdown_signal)
-            .add_worker(AxumServer)
+        let report = LifecycleManager::new()
+            .add_worker(OrderedWorker { name: "db", fail: false, events: events.clone() })
+            .add_restartable_worker(
+                {
+                    let events = events.clone();
+                    let runs = runs.clone();
+                    move || {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        OrderedWorker { name: "consumer", fail: true, events: events.clone() }
+                    }
+                },
+                WorkerOptions::new()
+                    .depends_on("db")
+                    .with_restart_policy(RestartPolicy::on_failure().with_max_restarts(2)),
+            )
             .serve()
             .await?;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let events = events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            ["consumer failed", "consumer failed", "consumer failed", "db signalled", "db stopped"]
+        );
+        assert!(report.workers[0].outcome.is_ok());
+        assert!(matches!(report.workers[1].outcome, WorkerOutcome::Error(Error::Dummy)));
         Ok(())
     }
 
     #[tokio::test]
     #[cfg_attr(miri, ignore)]
-    async fn test_with_axum_server_and_dummy_workers() -> Result<(), Error> {
-        let shutdown_signal = spawn_killer_task();
+    async fn test_restart_after_panic() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
 
-        LifecycleManager::new()
-            .with_custom_shutdown(shutdown_signal)
-            .add_worker(AxumServer)
-            .add_worker(DummyWorker::new(1))
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(300)))
+            .add_restartable_worker_fn(
+                "flaky-worker",
+                WorkerOptions::new().with_restart_policy(RestartPolicy::on_failure()),
+                {
+                    let runs = runs.clone();
+                    move |shutdown_signal| {
+                        let run = runs.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async move {
+                            assert!(run > 0, "first run is broken");
+                            shutdown_signal.await;
+                            Ok(())
+                        })
+                    }
+                },
+            )
+            .add_restartable_worker_fn(
+                "broken-worker",
+                WorkerOptions::new()
+                    .with_restart_policy(RestartPolicy::on_failure().with_max_restarts(1)),
+                |_shutdown_signal| Box::pin(async { panic!("worker is broken") }),
+            )
             .serve()
             .await?;
 
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(report.workers[0].outcome.is_ok());
+        let panicked: Vec<_> = report
+            .panicked()
+            .map(|worker| (worker.name.as_str(), worker.outcome.to_string()))
+            .collect();
+        assert_eq!(panicked, [("broken-worker", "panicked: worker is broken".to_string())]);
         Ok(())
     }
 
-    #[cfg(unix)]
     #[tokio::test]
     #[cfg_attr(miri, ignore)]
-    async fn test_with_axum_server_and_dummy_workers_with_unix_signal() -> Result<(), Error> {
-        spawn_killer_thread();
+    async fn test_lifecycle_events() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let hooks = Arc::new(Mutex::new(Vec::new()));
 
-        LifecycleManager::<Error>::new()
-            .add_worker(AxumServer)
-            .add_worker(DummyWorker::new(0))
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(300)))
+            .with_pre_shutdown_hook({
+                let hooks = hooks.clone();
+                move |reason| hooks.lock().unwrap().push(format!("pre-shutdown: {reason}"))
+            })
+            .with_post_shutdown_hook({
+                let hooks = hooks.clone();
+                move |report| {
+                    hooks.lock().unwrap().push(format!("post-shutdown: {}", report.is_success()));
+                }
+            })
+            .add_restartable_worker_fn(
+                "flaky-worker",
+                WorkerOptions::new().with_restart_policy(RestartPolicy::on_failure()),
+                {
+                    let runs = runs.clone();
+                    move |shutdown_signal| {
+                        let run = runs.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async move {
+                            if run == 0 {
+                                return DummySnafu.fail();
+                            }
+                            shutdown_signal.await;
+                            Ok(())
+                        })
+                    }
+                },
+            );
+        let mut events = lifecycle_manager.events();
+        let collector = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            while let Some(event) = events.recv().await {
+                collected.push(event);
+            }
+            collected
+        });
+
+        let report = lifecycle_manager.serve().await?;
+        assert!(report.is_success());
+        let events = collector.await.unwrap();
+
+        let worker = "flaky-worker".to_string();
+        let worker_events: Vec<_> = events
+            .iter()
+            .filter(|event| {
+                !matches!(
+                    event,
+                    LifecycleEvent::SignalReceived { .. }
+                        | LifecycleEvent::ShutdownStateChanged { .. }
+                )
+            })
+            .cloned()
+            .collect();
+        assert_eq!(
+            worker_events,
+            [
+                LifecycleEvent::WorkerSpawned { worker: worker.clone() },
+                LifecycleEvent::WorkerStarted { worker: worker.clone() },
+                LifecycleEvent::WorkerFailed { worker: worker.clone(), error: "Dummy".to_string() },
+                LifecycleEvent::WorkerRestarted { worker: worker.clone(), restarts: 1 },
+                LifecycleEvent::WorkerStopped { worker, outcome: "ok".to_string() },
+            ]
+        );
+        assert!(events.contains(&LifecycleEvent::SignalReceived { reason: ShutdownReason::Custom }));
+        assert!(events.contains(&LifecycleEvent::ShutdownStateChanged {
+            state: ShutdownState::ShuttingDown
+        }));
+
+        assert_eq!(
+            *hooks.lock().unwrap(),
+            [
+                format!("pre-shutdown: {}", ShutdownReason::Custom),
+                "post-shutdown: true".to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_metrics_recorder() -> Result<(), Error> {
+        let recorder = PrometheusRecorder::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(300)))
+            .with_metrics_recorder(recorder.clone())
+            .add_restartable_worker_fn(
+                "flaky-worker",
+                WorkerOptions::new().with_restart_policy(RestartPolicy::on_failure()),
+                {
+                    let runs = runs.clone();
+                    move |shutdown_signal| {
+                        let run = runs.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async move {
+                            if run == 0 {
+                                return DummySnafu.fail();
+                            }
+                            shutdown_signal.await;
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            Ok(())
+                        })
+                    }
+                },
+            )
             .serve()
             .await?;
+        assert!(report.is_success());
+
+        let metrics = recorder.render();
+        let sample = |prefix: &str| {
+            metrics
+                .lines()
+                .find_map(|line| line.strip_prefix(prefix)?.strip_prefix(' '))
+                .unwrap_or_else(|| panic!("{prefix} is not rendered"))
+                .parse::<f64>()
+                .unwrap()
+        };
+        assert_eq!(sample("lifecycle_worker_up{worker=\"flaky-worker\"}"), 0.0);
+        assert_eq!(sample("lifecycle_worker_restarts_total{worker=\"flaky-worker\"}"), 1.0);
+        assert_eq!(
+            sample("lifecycle_worker_last_exit{worker=\"flaky-worker\",reason=\"ok\"}"),
+            1.0
+        );
+        assert!(
+            sample("lifecycle_worker_shutdown_duration_seconds{worker=\"flaky-worker\"}") >= 0.1
+        );
+        assert_eq!(sample("lifecycle_shutdown_state{state=\"shutting_down\"}"), 1.0);
+        assert_eq!(sample("lifecycle_shutdown_state{state=\"wait_for_signal\"}"), 0.0);
+        assert!(sample("lifecycle_shutdown_state_seconds_total{state=\"wait_for_signal\"}") >= 0.3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_fail_fast() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name, fail| OrderedWorker { name, fail, events: events.clone() };
+
+        let report = LifecycleManager::new()
+            .with_failure_policy(FailurePolicy::FailFast)
+            .add_worker_with_options(
+                worker("cache", true),
+                WorkerOptions::new().with_critical(false),
+            )
+            .add_worker_fn("panicking-worker", |_shutdown_signal| {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    panic!("worker is broken")
+                })
+            })
+            .add_worker(worker("db", false))
+            .serve()
+            .await?;
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events, ["cache failed", "db signalled", "db stopped"]);
+
+        assert!(!report.is_success());
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::Error(Error::Dummy)));
+        assert!(matches!(
+            report.workers[1].outcome,
+            WorkerOutcome::Panicked(ref message) if message == "worker is broken"
+        ));
+        assert!(report.workers[2].outcome.is_ok());
+        assert!(report.workers[2].runtime >= Duration::from_millis(200));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_worker_group() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name| OrderedWorker { name, fail: false, events: events.clone() };
+
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(600)));
+        let group = lifecycle_manager.group("tenant");
+        let report = lifecycle_manager
+            .add_worker_with_options(worker("db"), WorkerOptions::new().with_group("tenant"))
+            .add_worker_with_options(
+                worker("consumer"),
+                WorkerOptions::new().with_group("tenant").depends_on("db"),
+            )
+            .add_worker(worker("server"))
+            .add_worker_fn("controller", {
+                let events = events.clone();
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        group.stop().await;
+                        events.lock().unwrap().push("group stopped".to_string());
+                        shutdown_signal.await;
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            [
+                "consumer signalled",
+                "consumer stopped",
+                "db signalled",
+                "db stopped",
+                "group stopped",
+                "server signalled",
+                "server stopped"
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_worker_group_stopped_while_shutting_down() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name| OrderedWorker { name, fail: false, events: events.clone() };
+
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_secs(1)));
+        let group = lifecycle_manager.group("tenant");
+        let report = lifecycle_manager
+            .add_worker_with_options(worker("db"), WorkerOptions::new().with_group("tenant"))
+            .add_worker_fn("controller", {
+                let events = events.clone();
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        shutdown_signal.await;
+                        group.stop().await;
+                        events.lock().unwrap().push("group stopped".to_string());
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events, ["db signalled", "db stopped", "group stopped"]);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_worker_group_dependents() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let worker = |name| OrderedWorker { name, fail: false, events: events.clone() };
+
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_secs(60)));
+        let group = lifecycle_manager.group("tenant");
+        let report = lifecycle_manager
+            .add_worker_with_options(worker("db"), WorkerOptions::new().with_group("tenant"))
+            .add_worker_with_options(worker("consumer"), WorkerOptions::new().depends_on("db"))
+            .add_worker_fn_with_options(
+                "stubborn",
+                WorkerOptions::new()
+                    .with_group("tenant")
+                    .with_shutdown_timeout(Duration::from_secs(1)),
+                |_shutdown_signal| Box::pin(std::future::pending()),
+            )
+            .add_worker_fn("controller", {
+                let events = events.clone();
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        let started_at = tokio::time::Instant::now();
+                        group.stop().await;
+                        assert_eq!(started_at.elapsed(), Duration::from_secs(1));
+                        events.lock().unwrap().push("group stopped".to_string());
+                        shutdown_signal.await;
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+
+        assert!(matches!(report.workers[2].outcome, WorkerOutcome::TimedOut));
+        let events = events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            [
+                "consumer signalled",
+                "consumer stopped",
+                "db signalled",
+                "db stopped",
+                "group stopped"
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_blocking_worker() -> Result<(), Error> {
+        struct PollingWorker {
+            polls: Arc<AtomicUsize>,
+        }
+
+        impl BlockingWorker for PollingWorker {
+            type Error = Error;
+
+            fn name(&self) -> &str { "polling-worker" }
+
+            fn serve(self, shutdown_token: ShutdownToken) -> Result<(), Self::Error> {
+                assert_eq!(std::thread::current().name(), Some("polling-worker"));
+                while shutdown_token.wait_timeout(Duration::from_millis(10)).is_none() {
+                    self.polls.fetch_add(1, Ordering::SeqCst);
+                }
+                assert_eq!(shutdown_token.reason(), Some(ShutdownReason::Custom));
+                Ok(())
+            }
+        }
+
+        struct PanickingWorker;
+
+        impl BlockingWorker for PanickingWorker {
+            type Error = Error;
+
+            fn name(&self) -> &str { "panicking-worker" }
+
+            fn serve(self, _shutdown_token: ShutdownToken) -> Result<(), Self::Error> {
+                panic!("worker is broken")
+            }
+        }
+
+        let polls = Arc::new(AtomicUsize::new(0));
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(200)))
+            .add_blocking_worker(PollingWorker { polls: polls.clone() })
+            .add_blocking_worker(PanickingWorker)
+            .serve()
+            .await?;
+
+        assert!(polls.load(Ordering::SeqCst) > 0);
+        assert!(report.workers[0].outcome.is_ok());
+        assert!(matches!(
+            report.workers[1].outcome,
+            WorkerOutcome::Panicked(ref message) if message == "worker is broken"
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "periodic")]
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_periodic_worker() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicUsize::new(0));
+
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(330)))
+            .add_periodic_worker(
+                "periodic-worker",
+                Schedule::interval(Duration::from_millis(100)).with_skip_if_running(true),
+                {
+                    let runs = runs.clone();
+                    let finished = finished.clone();
+                    move |shutdown_signal| {
+                        let (runs, finished) = (runs.clone(), finished.clone());
+                        Box::pin(async move {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            // the last run is still going when shutting down
+                            tokio::select! {
+                                () = tokio::time::sleep(Duration::from_millis(50)) => {}
+                                _ = shutdown_signal => {}
+                            }
+                            finished.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                    }
+                },
+            )
+            .add_periodic_worker(
+                "stuck-worker",
+                Schedule::interval(Duration::from_millis(50))
+                    .with_skip_if_running(true)
+                    .with_max_run_duration(Duration::from_millis(75)),
+                |_shutdown_signal| Box::pin(std::future::pending()),
+            )
+            .serve()
+            .await?;
+        assert!(report.workers[0].outcome.is_ok());
+        assert!(matches!(
+            report.workers[1].outcome,
+            WorkerOutcome::Error(Error::LifecycleManager {
+                source: super::Error::RunTimedOut { .. }
+            })
+        ));
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(finished.load(Ordering::SeqCst), 3);
+        assert!(Schedule::cron("* * *").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "periodic")]
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_periodic_worker_failure() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_secs(60)))
+            .add_periodic_worker("periodic-worker", Schedule::interval(Duration::from_secs(1)), {
+                let (runs, reasons) = (runs.clone(), reasons.clone());
+                move |shutdown_signal| {
+                    let (runs, reasons) = (runs.clone(), reasons.clone());
+                    Box::pin(async move {
+                        // the first run is in flight when the second one fails
+                        if runs.fetch_add(1, Ordering::SeqCst) > 0 {
+                            return DummySnafu.fail();
+                        }
+                        let reason = shutdown_signal.await;
+                        reasons.lock().unwrap().push(reason);
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::Error(Error::Dummy)));
+        assert!(report.workers[0].runtime < Duration::from_secs(3));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            reasons.lock().unwrap().as_slice(),
+            [ShutdownReason::WorkerFailed { worker }] if worker == "periodic-worker"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_shutdown_reason() -> Result<(), Error> {
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let worker_fn = |reasons: Arc<Mutex<Vec<_>>>| {
+            move |shutdown_signal: ShutdownSignal| -> futures::future::BoxFuture<'static, _> {
+                Box::pin(async move {
+                    assert_eq!(shutdown_signal.reason(), None);
+                    let reason = shutdown_signal.await;
+                    reasons.lock().unwrap().push(reason);
+                    Ok(())
+                })
+            }
+        };
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(100)))
+            .add_worker_fn("custom", worker_fn(reasons.clone()))
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        let report = LifecycleManager::new()
+            .with_failure_policy(FailurePolicy::FailFast)
+            .add_worker_fn("failing-worker", |_shutdown_signal| {
+                Box::pin(async { DummySnafu.fail() })
+            })
+            .add_worker_fn("fail-fast", worker_fn(reasons.clone()))
+            .serve()
+            .await?;
+        assert!(!report.is_success());
+
+        let reasons = reasons.lock().unwrap().clone();
+        assert_eq!(
+            reasons,
+            [
+                ShutdownReason::Custom,
+                ShutdownReason::WorkerFailed { worker: "failing-worker".to_string() }
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_reload_signal() -> Result<(), Error> {
+        let lifecycle_manager =
+            LifecycleManager::<Error>::new().with_reload_signal(ReloadSignal::Hangup);
+        let mut reload_receiver = lifecycle_manager.reload_receiver();
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let harness = TestHarness::new(lifecycle_manager.add_worker_fn("reloading-worker", {
+            let events = events.clone();
+            move |mut shutdown_signal| {
+                Box::pin(async move {
+                    loop {
+                        tokio::select! {
+                            reason = &mut shutdown_signal => {
+                                events.lock().unwrap().push(format!("shutdown: {reason}"));
+                                return Ok(());
+                            }
+                            Some(signal) = reload_receiver.recv() => {
+                                events.lock().unwrap().push(format!("reload: {signal}"));
+                            }
+                        }
+                    }
+                })
+            }
+        }));
+        let signal_sender = harness.signal_sender();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            signal_sender.hangup();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            signal_sender.terminate();
+        });
+        let (report, _) = harness.serve().await?;
+        assert!(report.is_success());
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events, ["reload: hangup signal", "shutdown: received terminate signal"]);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_run_blocking() -> Result<(), Error> {
+        let started_at = std::time::Instant::now();
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(async { tokio::time::sleep(Duration::from_millis(100)).await })
+            .add_worker_fn("leaking-worker", |shutdown_signal| {
+                Box::pin(async move {
+                    drop(tokio::task::spawn_blocking(|| {
+                        std::thread::sleep(Duration::from_secs(10));
+                    }));
+                    shutdown_signal.await;
+                    Ok(())
+                })
+            })
+            .run_blocking_with_options(
+                RuntimeOptions::new()
+                    .with_worker_threads(2)
+                    .with_shutdown_timeout(Duration::from_millis(100)),
+            )?;
+        assert!(report.is_success());
+        assert_eq!(report.exit_code(), std::process::ExitCode::SUCCESS);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_config_worker() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("config-worker-{}", std::process::id()));
+        std::fs::write(&path, "1").unwrap();
+
+        let lifecycle_manager =
+            LifecycleManager::<Error>::new().with_reload_signal(ReloadSignal::Hangup);
+        let config_worker = lifecycle_manager
+            .config_worker(&path, |content| content.trim().parse::<u32>())?
+            .with_poll_interval(Duration::from_secs(10));
+        let mut config = config_worker.subscribe();
+        assert_eq!(**config.borrow(), 1);
+        assert_eq!(config_worker.name(), format!("config-worker:{}", path.display()));
+
+        let harness = TestHarness::new(lifecycle_manager.add_worker(config_worker));
+        let signal_sender = harness.signal_sender();
+        let configs = tokio::spawn({
+            let path = path.clone();
+            async move {
+                let mut configs = Vec::new();
+                std::fs::write(&path, "22").unwrap();
+                tokio::time::sleep(Duration::from_secs(11)).await;
+                configs.push(**config.borrow_and_update());
+
+                std::fs::write(&path, "oops").unwrap();
+                signal_sender.hangup();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                configs.push(**config.borrow_and_update());
+
+                std::fs::write(&path, "333").unwrap();
+                signal_sender.hangup();
+                config.changed().await.unwrap();
+                configs.push(**config.borrow_and_update());
+
+                signal_sender.terminate();
+                configs
+            }
+        });
+        let (report, events) = harness.serve().await?;
+        std::fs::remove_file(&path).unwrap();
+        assert!(report.is_success());
+        assert_eq!(configs.await.unwrap(), [22, 22, 333]);
+
+        let config_events: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                LifecycleEvent::ConfigReloaded { .. } => Some("reloaded".to_string()),
+                LifecycleEvent::ConfigReloadFailed { error, .. } => Some(error),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            config_events,
+            [
+                "reloaded".to_string(),
+                format!("could not parse config {}: invalid digit found in string", path.display()),
+                "reloaded".to_string(),
+            ]
+        );
+
+        assert!(matches!(
+            LifecycleManager::<Error>::new().config_worker(&path, |content| content.parse::<u32>()),
+            Err(super::Error::LoadConfig { .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_process_worker() -> Result<(), Error> {
+        fn shell(script: &str) -> tokio::process::Command {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg("-c").arg(script);
+            command
+        }
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let failing = ProcessWorker::new("failing", {
+            let runs = runs.clone();
+            move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+                shell("echo failing >&2; exit 3")
+            }
+        });
+
+        let started_at = tokio::time::Instant::now();
+        let report = LifecycleManager::new()
+            .add_worker(ProcessWorker::new("sidecar", || {
+                shell("trap 'exit 0' TERM; echo ready; while :; do sleep 0.1; done")
+            }))
+            .add_worker_with_options(
+                ProcessWorker::new("stubborn", || {
+                    shell("trap '' TERM; while :; do sleep 0.1; done")
+                })
+                .with_kill_timeout(Duration::from_secs(1)),
+                WorkerOptions::new().with_shutdown_timeout(Duration::from_secs(5)),
+            )
+            // exceeding the restart limit shuts down the other processes
+            .add_restartable_worker(
+                move || failing.clone(),
+                WorkerOptions::new().with_restart_policy(
+                    RestartPolicy::exponential_backoff(
+                        Duration::from_millis(300),
+                        Duration::from_millis(300),
+                    )
+                    .with_max_restarts(1),
+                ),
+            )
+            .serve()
+            .await?;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(report.workers[0].outcome.is_ok());
+        // the process ignoring the stop signal is killed after the kill timeout
+        assert!(report.workers[1].outcome.is_ok());
+        assert!(started_at.elapsed() >= Duration::from_secs(1));
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            &report.workers[2].outcome,
+            WorkerOutcome::Error(Error::LifecycleManager { source: super::Error::ProcessExited { worker, status } })
+                if worker == "failing" && status.code() == Some(3)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_drain_tracker() -> Result<(), Error> {
+        let drained = Arc::new(Mutex::new(Vec::new()));
+        let listener = |stuck: bool| {
+            let drained = drained.clone();
+            move |mut shutdown_signal: ShutdownSignal| {
+                Box::pin(async move {
+                    let tracker = shutdown_signal.drain_tracker();
+                    for connection in 0..3 {
+                        let guard = tracker.track();
+                        tokio::spawn(async move {
+                            guard.draining().await;
+                            if stuck && connection == 0 {
+                                std::future::pending::<()>().await;
+                            }
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                        });
+                    }
+                    assert_eq!(tracker.in_flight(), 3);
+
+                    // stop accepting connections
+                    (&mut shutdown_signal).await;
+                    let in_flight = tracker.drain().await;
+                    drained.lock().unwrap().push(in_flight);
+                    Ok(())
+                }) as futures::future::BoxFuture<'static, _>
+            }
+        };
+
+        let started_at = tokio::time::Instant::now();
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_secs(1)))
+            .with_timeout(Duration::from_secs(3))
+            .add_worker_fn("tcp", listener(false))
+            .add_worker_fn("http", listener(true))
+            .add_worker_fn_with_options(
+                "grpc",
+                WorkerOptions::new().with_shutdown_timeout(Duration::from_secs(5)),
+                listener(true),
+            )
+            .serve()
+            .await?;
+
+        // the stuck connections are waited for until the deadline of the
+        // lifecycle manager, which is earlier than the one of the worker
+        assert_eq!(started_at.elapsed(), Duration::from_secs(4));
+        assert_eq!(drained.lock().unwrap().as_slice(), [0, 1, 1]);
+        assert!(report.workers[0].outcome.is_ok());
+        assert_eq!(report.workers[0].in_flight, 0);
+        assert!(report.workers[1].outcome.is_ok());
+        assert_eq!(report.workers[1].in_flight, 1);
+        assert_eq!(report.workers[2].in_flight, 1);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_instance_lock() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("instance-lock-{}", std::process::id()));
+        let started_at = tokio::time::Instant::now();
+        let lifecycle_manager = |instance_lock: InstanceLock, shutdown_after: u64| {
+            let started = Arc::new(Mutex::new(None));
+            let lifecycle_manager = LifecycleManager::<Error>::new()
+                .with_instance_lock(instance_lock)
+                .with_custom_shutdown(tokio::time::sleep(Duration::from_secs(shutdown_after)))
+                .add_worker_fn("cron", {
+                    let started = started.clone();
+                    move |shutdown_signal| {
+                        Box::pin(async move {
+                            *started.lock().unwrap() = Some(started_at.elapsed());
+                            shutdown_signal.await;
+                            Ok(())
+                        })
+                    }
+                });
+            (lifecycle_manager, started)
+        };
+
+        // another instance holds the lock until the third retry
+        let other_instance = std::fs::File::create(&path).unwrap();
+        other_instance.lock().unwrap();
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(2500)).await;
+            drop(other_instance);
+        });
+
+        let (failing, _) = lifecycle_manager(InstanceLock::new(&path), 10);
+        let err = failing.serve().await.unwrap_err();
+        assert!(matches!(err, super::Error::InstanceLocked { .. }));
+
+        // shut down before the lock is released
+        let (impatient, started) =
+            lifecycle_manager(InstanceLock::new(&path).with_wait(Duration::from_secs(1)), 1);
+        let report = impatient.serve().await?;
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::NotStarted));
+        assert_eq!(report.shutdown_reason, Some(ShutdownReason::Custom));
+        assert_eq!(*started.lock().unwrap(), None);
+
+        let (waiting, started) =
+            lifecycle_manager(InstanceLock::new(&path).with_wait(Duration::from_secs(1)), 5);
+        let report = waiting.serve().await?;
+        assert!(report.is_success());
+        assert_eq!(*started.lock().unwrap(), Some(Duration::from_secs(3)));
+        release.await.unwrap();
+
+        // the lock is released after the shutdown
+        let (next, started) = lifecycle_manager(InstanceLock::new(&path), 1);
+        assert!(next.serve().await?.is_success());
+        assert_eq!(*started.lock().unwrap(), Some(Duration::from_secs(6)));
+
+        std::fs::remove_file(&path).unwrap();
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_deadline() -> Result<(), Error> {
+        let deadline = Arc::new(Mutex::new(None));
+        let harness = TestHarness::new(
+            LifecycleManager::<Error>::new().with_timeout(Duration::from_secs(5)).add_worker_fn(
+                "worker",
+                {
+                    let deadline = deadline.clone();
+                    move |mut shutdown_signal| {
+                        Box::pin(async move {
+                            (&mut shutdown_signal).await;
+                            *deadline.lock().unwrap() = shutdown_signal.deadline();
+                            Ok(())
+                        })
+                    }
+                },
+            ),
+        );
+        let signal_sender = harness.signal_sender();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            signal_sender.terminate();
+        });
+
+        let started_at = tokio::time::Instant::now();
+        let (report, _) = harness.serve().await?;
+        assert!(report.is_success());
+        // the timeout of the lifecycle manager is visible after one signal
+        assert_eq!(*deadline.lock().unwrap(), Some(started_at + Duration::from_secs(15)));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_harness() -> Result<(), Error> {
+        let harness = TestHarness::new(
+            LifecycleManager::<Error>::new().with_timeout(Duration::from_secs(5)).add_worker_fn(
+                "stuck-worker",
+                |shutdown_signal| {
+                    Box::pin(async move {
+                        shutdown_signal.await;
+                        std::future::pending().await
+                    })
+                },
+            ),
+        );
+        let signal_sender = harness.signal_sender();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            signal_sender.terminate();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            signal_sender.interrupt();
+        });
+
+        let started_at = tokio::time::Instant::now();
+        let (report, events) = harness.serve().await?;
+        // the second signal escalates at the deadline set by the first one
+        assert_eq!(started_at.elapsed(), Duration::from_secs(65));
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::Cancelled));
+
+        let worker = "stuck-worker".to_string();
+        assert_eq!(
+            events,
+            [
+                LifecycleEvent::WorkerSpawned { worker: worker.clone() },
+                LifecycleEvent::WorkerStarted { worker: worker.clone() },
+                LifecycleEvent::ShutdownStateChanged { state: ShutdownState::WaitForSignal },
+                LifecycleEvent::SignalReceived {
+                    reason: ShutdownReason::Signal(Signal::Terminate)
+                },
+                LifecycleEvent::ShutdownStateChanged { state: ShutdownState::ShuttingDown },
+                LifecycleEvent::SignalReceived {
+                    reason: ShutdownReason::Signal(Signal::Interrupt)
+                },
+                LifecycleEvent::ShutdownStateChanged { state: ShutdownState::Aborting },
+                LifecycleEvent::WorkerStopped { worker, outcome: "cancelled".to_string() },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_circuit_breaker() -> Result<(), Error> {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let harness = TestHarness::new(
+            LifecycleManager::<Error>::new()
+                .with_circuit_breaker(CircuitBreaker::new(3, Duration::from_secs(10)))
+                .add_worker_fn("api", |shutdown_signal| {
+                    Box::pin(async move {
+                        shutdown_signal.await;
+                        Ok(())
+                    })
+                })
+                .add_restartable_worker_fn(
+                    "flaky-worker",
+                    WorkerOptions::new().with_restart_policy(RestartPolicy::exponential_backoff(
+                        Duration::from_secs(1),
+                        Duration::from_secs(1),
+                    )),
+                    {
+                        let runs = runs.clone();
+                        move |_shutdown_signal| {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            Box::pin(async { DummySnafu.fail() })
+                        }
+                    },
+                ),
+        );
+
+        let started_at = tokio::time::Instant::now();
+        let (report, events) = harness.serve().await?;
+        // the fourth failure within the window trips the circuit breaker
+        assert_eq!(started_at.elapsed(), Duration::from_secs(3));
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+
+        assert!(report.circuit_breaker_tripped());
+        assert!(!report.is_success());
+        assert_eq!(
+            report.shutdown_reason,
+            Some(ShutdownReason::CircuitBreakerTripped {
+                max_failures: 3,
+                window: Duration::from_secs(10)
+            })
+        );
+        assert!(report.workers[0].outcome.is_ok());
+        assert!(matches!(report.workers[1].outcome, WorkerOutcome::Error(Error::Dummy)));
+
+        let tripped: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event, LifecycleEvent::CircuitBreakerTripped { .. }))
+            .collect();
+        assert_eq!(
+            tripped,
+            [&LifecycleEvent::CircuitBreakerTripped { worker: "flaky-worker".to_string() }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_startup_order() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let report = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(500)))
+            .add_worker_fn_with_options(
+                "db",
+                WorkerOptions::new().with_started_notification(true),
+                {
+                    let events = events.clone();
+                    move |shutdown_signal| {
+                        Box::pin(async move {
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                            events.lock().unwrap().push("db started");
+                            shutdown_signal.notify_started();
+                            shutdown_signal.await;
+                            Ok(())
+                        })
+                    }
+                },
+            )
+            .add_worker_fn_with_options("app", WorkerOptions::new().depends_on("db"), {
+                let events = events.clone();
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        events.lock().unwrap().push("app spawned");
+                        shutdown_signal.await;
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events, ["db started", "app spawned"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_startup_failed() -> Result<(), Error> {
+        let spawned = Arc::new(AtomicUsize::new(0));
+
+        let report = LifecycleManager::new()
+            .add_worker_fn_with_options(
+                "db",
+                WorkerOptions::new().with_started_notification(true),
+                |_shutdown_signal| Box::pin(async { DummySnafu.fail() }),
+            )
+            .add_worker_fn_with_options("app", WorkerOptions::new().depends_on("db"), {
+                let spawned = spawned.clone();
+                move |_shutdown_signal| {
+                    Box::pin(async move {
+                        spawned.fetch_add(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                }
+            })
+            .serve()
+            .await?;
+
+        assert_eq!(spawned.load(Ordering::SeqCst), 0);
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::Error(Error::Dummy)));
+        assert!(matches!(
+            &report.workers[1].outcome,
+            WorkerOutcome::DependencyFailed { dependency } if dependency == "db"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_shutdown_timeout() -> Result<(), Error> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(100)))
+            .add_worker_fn_with_options(
+                "stuck-worker",
+                WorkerOptions::new()
+                    .depends_on("db")
+                    .with_shutdown_timeout(Duration::from_millis(200)),
+                |mut shutdown_signal| {
+                    Box::pin(async move {
+                        (&mut shutdown_signal).await;
+                        assert!(shutdown_signal.deadline().is_some());
+                        std::future::pending().await
+                    })
+                },
+            )
+            .add_worker(OrderedWorker { name: "db", fail: false, events: events.clone() })
+            .serve()
+            .await?;
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events, ["db signalled", "db stopped"]);
+        assert!(matches!(report.workers[0].outcome, WorkerOutcome::TimedOut));
+        assert!(report.workers[0].runtime < Duration::from_secs(1));
+        assert!(report.workers[1].outcome.is_ok());
+        Ok(())
+    }
+
+    #[cfg(feature = "health")]
+    async fn http_get(addr: SocketAddr, path: &str) -> (u16, String) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(format!("GET {path} HTTP/1.0\r\n\r\n").as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+
+    #[cfg(feature = "health")]
+    async fn http_status(addr: SocketAddr, path: &str) -> u16 { http_get(addr, path).await.0 }
+
+    #[cfg(feature = "health")]
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_health_worker() -> Result<(), Error> {
+        use super::{LIVENESS_PATH, METRICS_PATH, READINESS_PATH};
+
+        let recorder = PrometheusRecorder::new();
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(500)))
+            .with_metrics_recorder(recorder.clone());
+        let health_worker = lifecycle_manager
+            .health_worker(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))?
+            .with_metrics(recorder);
+        let addr = health_worker.local_addr().unwrap();
+        let readiness = health_worker.readiness_handle("app");
+
+        let report = lifecycle_manager
+            .add_worker(health_worker)
+            .add_worker_fn_with_options(
+                "app",
+                WorkerOptions::new().depends_on("health-endpoint"),
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        assert_eq!(http_status(addr, LIVENESS_PATH).await, 200);
+                        assert_eq!(http_status(addr, READINESS_PATH).await, 503);
+
+                        readiness.set_ready(true);
+                        let mut status = 0;
+                        for _ in 0..10 {
+                            status = http_status(addr, READINESS_PATH).await;
+                            if status == 200 {
+                                break;
+                            }
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                        }
+                        assert_eq!(status, 200);
+
+                        let (status, metrics) = http_get(addr, METRICS_PATH).await;
+                        assert_eq!(status, 200);
+                        assert!(metrics.contains("lifecycle_worker_up{worker=\"app\"} 1\n"));
+
+                        shutdown_signal.await;
+                        assert_eq!(http_status(addr, READINESS_PATH).await, 503);
+                        assert_eq!(http_status(addr, LIVENESS_PATH).await, 200);
+                        Ok(())
+                    })
+                },
+            )
+            .serve()
+            .await?;
+        assert!(report.is_success());
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "systemd"))]
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_systemd_notifier() -> Result<(), Error> {
+        use std::os::unix::net::UnixDatagram;
+
+        use super::SystemdNotifier;
+
+        let socket_path =
+            std::env::temp_dir().join(format!("lifecycle-manager-{}.sock", std::process::id()));
+        let _unused = std::fs::remove_file(&socket_path);
+        let socket = UnixDatagram::bind(&socket_path).unwrap();
+        socket.set_nonblocking(true).unwrap();
+
+        let lifecycle_manager = LifecycleManager::<Error>::new()
+            .with_custom_shutdown(tokio::time::sleep(Duration::from_millis(500)));
+        let notifier = SystemdNotifier::new(
+            &socket_path,
+            lifecycle_manager.shutdown_state(),
+            lifecycle_manager.workers_started(),
+        )
+        .with_watchdog_interval(Duration::from_millis(100));
+        let watchdog = notifier.watchdog_handle("app");
+
+        let report = lifecycle_manager
+            .add_worker(notifier)
+            .add_worker_fn_with_options(
+                "app",
+                WorkerOptions::new().depends_on("systemd-notifier").with_started_notification(true),
+                move |shutdown_signal| {
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        shutdown_signal.notify_started();
+                        // stall after a while, the watchdog is not pinged anymore
+                        for _ in 0..5 {
+                            watchdog.ping();
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                        }
+                        shutdown_signal.await;
+                        Ok(())
+                    })
+                },
+            )
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        let mut messages = Vec::new();
+        let mut buf = [0; 64];
+        while let Ok(len) = socket.recv(&mut buf) {
+            messages.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+        }
+        std::fs::remove_file(&socket_path).unwrap();
+
+        let ready = messages.iter().position(|message| message == "READY=1").unwrap();
+        let watchdogs = messages.iter().filter(|message| *message == "WATCHDOG=1").count();
+        assert_eq!(messages.last().map(String::as_str), Some("STOPPING=1"));
+        assert!(ready < messages.len() - 1);
+        // pinged every 50 milliseconds for the first ~200 milliseconds only
+        assert!((2..=6).contains(&watchdogs), "{messages:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_with_axum_server() -> Result<(), Error> {
+        let shutdown_signal = spawn_killer_task();
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(shutdown_signal)
+            .add_worker(AxumServer)
+            .serve()
+            .await?;
+        assert!(report.is_success());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_with_axum_server_and_dummy_workers() -> Result<(), Error> {
+        let shutdown_signal = spawn_killer_task();
+
+        let report = LifecycleManager::new()
+            .with_custom_shutdown(shutdown_signal)
+            .add_worker(AxumServer)
+            .add_worker(DummyWorker::new(1))
+            .serve()
+            .await?;
+        assert!(report.is_success());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_with_axum_server_and_dummy_workers_with_unix_signal() -> Result<(), Error> {
+        let harness = TestHarness::new(
+            LifecycleManager::<Error>::new().add_worker(AxumServer).add_worker(DummyWorker::new(0)),
+        );
+        let signal_sender = harness.signal_sender();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            signal_sender.terminate();
+        });
+
+        let (report, _) = harness.serve().await?;
+        assert!(report.is_success());
 
         Ok(())
     }
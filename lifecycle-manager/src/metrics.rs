@@ -0,0 +1,239 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use crate::ShutdownState;
+
+/// Records the metrics of a [`LifecycleManager`](crate::LifecycleManager),
+/// registered with
+/// [`with_metrics_recorder`](crate::LifecycleManager::with_metrics_recorder).
+///
+/// The methods are called from the tasks of the lifecycle manager and must not
+/// block. See [`PrometheusRecorder`] for a recorder rendering the Prometheus
+/// text format.
+pub trait MetricsRecorder: Send + Sync {
+    /// The worker is running, or not anymore, e.g. while it waits to be
+    /// restarted.
+    fn set_worker_up(&self, worker: &str, up: bool);
+
+    /// The worker is restarted once more.
+    fn increment_worker_restarts(&self, worker: &str);
+
+    /// The worker exited, with the [kind](crate::WorkerOutcome::kind) of its
+    /// outcome.
+    fn set_worker_exit_reason(&self, worker: &str, reason: &str);
+
+    /// The worker stopped `duration` after it was signalled.
+    fn record_worker_shutdown_duration(&self, worker: &str, duration: Duration);
+
+    /// The [`ShutdownState`] is advanced to `state`, after `previous` was the
+    /// state for `duration`.
+    fn set_shutdown_state(&self, state: ShutdownState, previous: ShutdownState, duration: Duration);
+}
+
+/// The recorder of a lifecycle manager, which records nothing if none is
+/// registered.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics {
+    recorder: Option<Arc<dyn MetricsRecorder>>,
+}
+
+impl Metrics {
+    pub fn new(recorder: Arc<dyn MetricsRecorder>) -> Self { Self { recorder: Some(recorder) } }
+
+    pub fn record(&self, f: impl FnOnce(&dyn MetricsRecorder)) {
+        if let Some(recorder) = &self.recorder {
+            f(recorder.as_ref());
+        }
+    }
+}
+
+#[derive(Default)]
+struct WorkerMetrics {
+    up: bool,
+    restarts: u32,
+    exit_reason: Option<String>,
+    shutdown_duration: Option<Duration>,
+}
+
+struct Registry {
+    workers: BTreeMap<String, WorkerMetrics>,
+    state: ShutdownState,
+    state_entered_at: Instant,
+    state_durations: BTreeMap<&'static str, Duration>,
+}
+
+/// A [`MetricsRecorder`] keeping the metrics in memory and rendering them in
+/// the Prometheus text format.
+#[cfg_attr(
+    feature = "health",
+    doc = "It is served by [`HealthWorker::with_metrics`](crate::HealthWorker::with_metrics)."
+)]
+/// Clones share the same metrics.
+#[derive(Clone)]
+pub struct PrometheusRecorder {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Default for PrometheusRecorder {
+    fn default() -> Self {
+        Self {
+            registry: Arc::new(Mutex::new(Registry {
+                workers: BTreeMap::new(),
+                state: ShutdownState::default(),
+                state_entered_at: Instant::now(),
+                state_durations: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl PrometheusRecorder {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    fn with_worker(&self, worker: &str, f: impl FnOnce(&mut WorkerMetrics)) {
+        let mut registry = self.registry.lock().expect("lock is not poisoned; qed");
+        f(registry.workers.entry(worker.to_string()).or_default());
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().expect("lock is not poisoned; qed");
+        let workers = &registry.workers;
+        let states = [
+            ShutdownState::Initial,
+            ShutdownState::WaitForSignal,
+            ShutdownState::ShuttingDown,
+            ShutdownState::Aborting,
+        ];
+
+        let mut output = String::new();
+        write_family(
+            &mut output,
+            ("lifecycle_worker_up", "gauge", "Whether the worker is running."),
+            workers.iter().map(|(worker, metrics)| {
+                (vec![("worker", worker.as_str())], f64::from(u8::from(metrics.up)))
+            }),
+        );
+        write_family(
+            &mut output,
+            ("lifecycle_worker_restarts_total", "counter", "How often the worker is restarted."),
+            workers.iter().map(|(worker, metrics)| {
+                (vec![("worker", worker.as_str())], f64::from(metrics.restarts))
+            }),
+        );
+        write_family(
+            &mut output,
+            ("lifecycle_worker_last_exit", "gauge", "The reason the worker last exited."),
+            workers.iter().filter_map(|(worker, metrics)| {
+                let reason = metrics.exit_reason.as_deref()?;
+                Some((vec![("worker", worker.as_str()), ("reason", reason)], 1.0))
+            }),
+        );
+        write_family(
+            &mut output,
+            (
+                "lifecycle_worker_shutdown_duration_seconds",
+                "gauge",
+                "How long the worker took to stop after it was signalled.",
+            ),
+            workers.iter().filter_map(|(worker, metrics)| {
+                let duration = metrics.shutdown_duration?;
+                Some((vec![("worker", worker.as_str())], duration.as_secs_f64()))
+            }),
+        );
+        write_family(
+            &mut output,
+            ("lifecycle_shutdown_state", "gauge", "Whether the process is in the shutdown state."),
+            states.iter().map(|&state| {
+                (vec![("state", state_label(state))], f64::from(u8::from(state == registry.state)))
+            }),
+        );
+        write_family(
+            &mut output,
+            (
+                "lifecycle_shutdown_state_seconds_total",
+                "counter",
+                "How long the process has been in the shutdown state.",
+            ),
+            states.iter().map(|&state| {
+                let mut duration =
+                    registry.state_durations.get(state_label(state)).copied().unwrap_or_default();
+                if state == registry.state {
+                    duration += registry.state_entered_at.elapsed();
+                }
+                (vec![("state", state_label(state))], duration.as_secs_f64())
+            }),
+        );
+        output
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn set_worker_up(&self, worker: &str, up: bool) {
+        self.with_worker(worker, |metrics| metrics.up = up);
+    }
+
+    fn increment_worker_restarts(&self, worker: &str) {
+        self.with_worker(worker, |metrics| metrics.restarts += 1);
+    }
+
+    fn set_worker_exit_reason(&self, worker: &str, reason: &str) {
+        self.with_worker(worker, |metrics| metrics.exit_reason = Some(reason.to_string()));
+    }
+
+    fn record_worker_shutdown_duration(&self, worker: &str, duration: Duration) {
+        self.with_worker(worker, |metrics| metrics.shutdown_duration = Some(duration));
+    }
+
+    fn set_shutdown_state(
+        &self,
+        state: ShutdownState,
+        previous: ShutdownState,
+        duration: Duration,
+    ) {
+        let mut registry = self.registry.lock().expect("lock is not poisoned; qed");
+        *registry.state_durations.entry(state_label(previous)).or_default() += duration;
+        registry.state = state;
+        registry.state_entered_at = Instant::now();
+    }
+}
+
+const fn state_label(state: ShutdownState) -> &'static str {
+    match state {
+        ShutdownState::Initial => "initial",
+        ShutdownState::WaitForSignal => "wait_for_signal",
+        ShutdownState::ShuttingDown => "shutting_down",
+        ShutdownState::Aborting => "aborting",
+    }
+}
+
+/// Writes a metric family with its `(name, type, help)` header and its
+/// samples.
+fn write_family<'a>(
+    output: &mut String,
+    (name, kind, help): (&str, &str, &str),
+    samples: impl Iterator<Item = (Vec<(&'a str, &'a str)>, f64)>,
+) {
+    let _unused = writeln!(output, "# HELP {name} {help}\n# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        let labels = labels
+            .iter()
+            .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let _unused = writeln!(output, "{name}{{{labels}}} {value}");
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
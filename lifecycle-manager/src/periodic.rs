@@ -0,0 +1,408 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use rand::Rng;
+use snafu::ensure;
+use tokio::{sync::watch, time::Instant};
+
+use crate::{
+    error::{InvalidCronExpressionSnafu, Result, RunTimedOutSnafu},
+    shutdown_signal::Shutdown,
+    Error, ShutdownReason, ShutdownSignal,
+};
+
+/// When a periodic worker added with
+/// [`LifecycleManager::add_periodic_worker`](crate::LifecycleManager::add_periodic_worker)
+/// runs its job, enabled with the `periodic` feature.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    timing: Timing,
+    jitter: Duration,
+    skip_if_running: bool,
+    max_run_duration: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+enum Timing {
+    Interval(Duration),
+    Cron(CronExpression),
+}
+
+impl Schedule {
+    /// Runs the job every `interval`, starting one `interval` after the worker
+    /// is spawned. Runs which are missed are skipped.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    #[inline]
+    #[must_use]
+    pub fn interval(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "interval of a schedule must be non-zero");
+        Self::new(Timing::Interval(interval))
+    }
+
+    /// Runs the job whenever the cron expression in UTC matches, e.g.
+    /// `*/15 9-17 * * 1-5`.
+    ///
+    /// The five fields are minute, hour, day of month, month and day of week
+    /// (0 or 7 is Sunday). Each field is `*` or a comma separated list of
+    /// values and ranges, optionally followed by a step like `*/5` or `1-30/2`.
+    ///
+    /// # Errors
+    ///
+    /// If `expression` is not a valid cron expression.
+    #[inline]
+    pub fn cron(expression: &str) -> Result<Self> {
+        CronExpression::parse(expression).map(|cron| Self::new(Timing::Cron(cron)))
+    }
+
+    const fn new(timing: Timing) -> Self {
+        Self { timing, jitter: Duration::ZERO, skip_if_running: false, max_run_duration: None }
+    }
+
+    /// Delays every run by a random duration up to `jitter`, e.g. for
+    /// spreading the load of many processes with the same schedule.
+    #[inline]
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets whether a run is skipped while the previous run is still going.
+    /// Runs may overlap by default.
+    #[inline]
+    #[must_use]
+    pub const fn with_skip_if_running(mut self, skip_if_running: bool) -> Self {
+        self.skip_if_running = skip_if_running;
+        self
+    }
+
+    /// Cancels a run which takes longer than `max_run_duration`, which fails
+    /// the worker with [`Error::RunTimedOut`](crate::Error::RunTimedOut).
+    #[inline]
+    #[must_use]
+    pub const fn with_max_run_duration(mut self, max_run_duration: Duration) -> Self {
+        self.max_run_duration = Some(max_run_duration);
+        self
+    }
+
+    /// Returns the instant of the run following the one at `previous`, or
+    /// `None` if there is none.
+    fn next_run(&self, previous: Instant) -> Option<Instant> {
+        let now = Instant::now();
+        let next = match &self.timing {
+            Timing::Interval(interval) => (previous + *interval).max(now),
+            Timing::Cron(cron) => {
+                let system_now = SystemTime::now();
+                let previous = system_now.checked_sub(now.saturating_duration_since(previous))?;
+                let next = cron.next_after(previous.max(system_now))?;
+                now + next.duration_since(system_now).unwrap_or_default()
+            }
+        };
+        Some(next)
+    }
+
+    fn jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=self.jitter)
+    }
+}
+
+/// Runs `job` according to `schedule` until the worker is signalled, then
+/// waits for the runs in flight. Each run gets its own shutdown signal.
+///
+/// An error of a run stops the worker, so that it is handled by the restart
+/// and failure policies. The other runs in flight are signalled and waited for
+/// first, the first error is returned.
+pub(crate) async fn run_periodic<E, J>(
+    name: String,
+    schedule: Schedule,
+    job: Arc<Mutex<J>>,
+    mut shutdown_signal: ShutdownSignal,
+) -> Result<(), E>
+where
+    E: From<Error> + Send + 'static,
+    J: FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>>,
+{
+    let mut runs = FuturesUnordered::new();
+    let mut next_run = schedule.next_run(Instant::now());
+    let mut run_at = next_run.map(|next_run| next_run + schedule.jitter());
+    // the runs are signalled when the worker is, or when a run fails
+    let (runs_tx, runs_rx) = watch::channel(None);
+    let mut error = None;
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_signal => break,
+            Some(result) = runs.next() => if let Err(err) = result {
+                tracing::info!("Run of worker {name} failed, stop the runs in flight");
+                runs_tx.send_replace(Some(Shutdown::new(
+                    ShutdownReason::WorkerFailed { worker: name.clone() },
+                    Instant::now(),
+                )));
+                error = Some(err);
+                break;
+            },
+            () = tokio::time::sleep_until(run_at.unwrap_or_else(Instant::now)),
+                if run_at.is_some() =>
+            {
+                if schedule.skip_if_running && !runs.is_empty() {
+                    tracing::info!("Previous run of worker {name} is not finished, skip this run");
+                } else {
+                    let run = (job.lock().expect("lock is not poisoned; qed"))(
+                        shutdown_signal.subscribe_to(&name, runs_rx.clone()),
+                    );
+                    runs.push(limit_run_duration(&name, run, schedule.max_run_duration));
+                }
+
+                next_run = next_run.and_then(|previous| schedule.next_run(previous));
+                run_at = next_run.map(|next_run| next_run + schedule.jitter());
+                if run_at.is_none() {
+                    tracing::warn!("Schedule of worker {name} has no more runs");
+                }
+            }
+        }
+    }
+
+    let mut shutdown_rx = shutdown_signal.shutdown_rx();
+    loop {
+        // e.g. the deadline is set by another shutdown signal
+        if let Some(shutdown) = shutdown_rx.borrow_and_update().clone() {
+            runs_tx.send_replace(Some(shutdown));
+        }
+        tokio::select! {
+            result = runs.next() => match result {
+                Some(Err(err)) => {
+                    error.get_or_insert(err);
+                }
+                Some(Ok(())) => {}
+                None => break,
+            },
+            Ok(()) = shutdown_rx.changed() => {}
+        }
+    }
+    error.map_or(Ok(()), Err)
+}
+
+fn limit_run_duration<E>(
+    name: &str,
+    run: BoxFuture<'static, Result<(), E>>,
+    max_run_duration: Option<Duration>,
+) -> BoxFuture<'static, Result<(), E>>
+where
+    E: From<Error> + 'static,
+{
+    let Some(max_run_duration) = max_run_duration else { return run };
+    let worker = name.to_string();
+    tokio::time::timeout(max_run_duration, run)
+        .map(move |result| {
+            result.unwrap_or_else(|_elapsed| {
+                tracing::warn!(
+                    "Run of worker {worker} is not finished in {} milliseconds, cancel it",
+                    max_run_duration.as_millis()
+                );
+                Err(RunTimedOutSnafu { worker, max_run_duration }.build().into())
+            })
+        })
+        .boxed()
+}
+
+/// A cron expression with the fields minute, hour, day of month, month and
+/// day of week, each as a bit set of the matching values.
+#[derive(Clone, Eq, PartialEq)]
+struct CronExpression {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether both day fields are restricted, then either of them matches.
+    either_day: bool,
+}
+
+impl CronExpression {
+    fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        ensure!(
+            fields.len() == 5,
+            InvalidCronExpressionSnafu { expression, reason: "expected 5 fields" }
+        );
+
+        let field = |index: usize, min: u64, max: u64| {
+            parse_field(fields[index], min, max).ok_or_else(|| {
+                InvalidCronExpressionSnafu {
+                    expression,
+                    reason: format!("invalid field `{}`", fields[index]),
+                }
+                .build()
+            })
+        };
+        let mut days_of_week = field(4, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)?,
+            days_of_month: field(2, 1, 31)?,
+            months: field(3, 1, 12)?,
+            days_of_week,
+            either_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+
+    /// Returns the first matching minute after `after`, looking ahead for
+    /// five years at most.
+    fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        const MINUTES_PER_DAY: u64 = 24 * 60;
+
+        let mut minute = after.duration_since(UNIX_EPOCH).ok()?.as_secs() / 60 + 1;
+        let limit = minute + 5 * 366 * MINUTES_PER_DAY;
+        while minute < limit {
+            let days = minute / MINUTES_PER_DAY;
+            if !self.matches_day(days) {
+                minute = (days + 1) * MINUTES_PER_DAY;
+                continue;
+            }
+
+            let hour = minute % MINUTES_PER_DAY / 60;
+            if !has(self.hours, hour) {
+                minute = days * MINUTES_PER_DAY + (hour + 1) * 60;
+                continue;
+            }
+
+            if has(self.minutes, minute % 60) {
+                return Some(UNIX_EPOCH + Duration::from_secs(minute * 60));
+            }
+            minute += 1;
+        }
+        None
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (month, day) = month_and_day(days_since_epoch);
+        // 1970-01-01 is a Thursday
+        let weekday = (days_since_epoch + 4) % 7;
+
+        let day_of_month = has(self.days_of_month, day);
+        let day_of_week = has(self.days_of_week, weekday);
+        has(self.months, month)
+            && if self.either_day {
+                day_of_month || day_of_week
+            } else {
+                day_of_month && day_of_week
+            }
+    }
+}
+
+impl fmt::Debug for CronExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CronExpression").field(&self.expression).finish()
+    }
+}
+
+const fn has(set: u64, value: u64) -> bool { set & (1 << value) != 0 }
+
+/// Parses a field like `*`, `*/5`, `1,3-5` or `10-50/10` into a bit set,
+/// returns `None` if it is invalid or out of `min..=max`.
+fn parse_field(field: &str, min: u64, max: u64) -> Option<u64> {
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().ok().filter(|&step| step > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, if part.contains('/') { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        set |= (start..=end).step_by(usize::try_from(step).ok()?).fold(0, |set, v| set | 1 << v);
+    }
+    Some(set)
+}
+
+/// Converts days since the unix epoch to the month and the day of month, see
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+const fn month_and_day(days_since_epoch: u64) -> (u64, u64) {
+    let days = days_since_epoch + 719_468;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    (month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{month_and_day, CronExpression};
+
+    #[test]
+    fn test_month_and_day() {
+        assert_eq!(month_and_day(0), (1, 1));
+        // 2000-02-29
+        assert_eq!(month_and_day(11_016), (2, 29));
+        // 2026-10-17
+        assert_eq!(month_and_day(20_743), (10, 17));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(CronExpression::parse("* * * * *").is_ok());
+        assert!(CronExpression::parse("*/15 9-17 * * 1-5").is_ok());
+        assert!(CronExpression::parse("0 0 1,15 * 7").is_ok());
+        assert!(CronExpression::parse("* * * *").is_err());
+        assert!(CronExpression::parse("60 * * * *").is_err());
+        assert!(CronExpression::parse("*/0 * * * *").is_err());
+        assert!(CronExpression::parse("5-1 * * * *").is_err());
+        assert!(CronExpression::parse("* * 0 * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2026-10-17T10:07:30Z, a Saturday
+        let now = UNIX_EPOCH + Duration::from_secs(1_792_231_650);
+        let next = |expression| {
+            let next = CronExpression::parse(expression).unwrap().next_after(now).unwrap();
+            next.duration_since(UNIX_EPOCH).unwrap().as_secs()
+        };
+
+        // 2026-10-17T10:08:00Z
+        assert_eq!(next("* * * * *"), 1_792_231_680);
+        // 2026-10-17T10:15:00Z
+        assert_eq!(next("*/15 * * * *"), 1_792_232_100);
+        // 2026-10-19T09:00:00Z, the next Monday
+        assert_eq!(next("0 9 * * 1"), 1_792_400_400);
+        // 2026-10-21T00:00:00Z, the first of the month or a Wednesday
+        assert_eq!(next("0 0 1 * 3"), 1_792_540_800);
+        assert_eq!(CronExpression::parse("0 0 30 2 *").unwrap().next_after(now), None);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{fmt, io, marker::PhantomData, process::Stdio, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use snafu::ResultExt;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::{Child, Command},
+    time::Instant,
+};
+
+use crate::{
+    error::{ProcessExitedSnafu, SpawnProcessSnafu, WaitProcessSnafu},
+    Error, ShutdownSignal, Worker,
+};
+
+type CommandFactory = Arc<dyn Fn() -> Command + Send + Sync>;
+
+/// A worker running a child process, e.g. a sidecar binary, enabled with the
+/// `process` feature.
+///
+/// The command is created anew for every run, so the worker can be added
+/// with
+/// [`add_restartable_worker`](crate::LifecycleManager::add_restartable_worker)
+/// and a factory cloning it to restart the process according to its
+/// [`RestartPolicy`](crate::RestartPolicy). Every line the process writes to
+/// stdout or stderr is logged with the name of the worker.
+///
+/// The worker fails if the process exits with a failure status on its own.
+/// When it is signalled, the [`StopSignal`] is sent to the process and the
+/// worker waits for it to exit. If it does not exit within the
+/// [kill timeout](Self::with_kill_timeout), the process is killed with
+/// `SIGKILL`. The process is also killed if the worker is aborted before,
+/// e.g. after its
+/// [shutdown timeout](crate::WorkerOptions::with_shutdown_timeout).
+pub struct ProcessWorker<E> {
+    name: String,
+    command: CommandFactory,
+    stop_signal: StopSignal,
+    kill_timeout: Option<Duration>,
+    _error: PhantomData<fn() -> E>,
+}
+
+/// The signal sent to the process of a [`ProcessWorker`] to shut it down.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum StopSignal {
+    /// `SIGTERM`
+    #[default]
+    Terminate,
+
+    /// `SIGINT`
+    Interrupt,
+
+    /// `SIGQUIT`
+    Quit,
+
+    /// `SIGHUP`
+    Hangup,
+
+    /// `SIGUSR1`
+    UserDefined1,
+
+    /// `SIGUSR2`
+    UserDefined2,
+}
+
+impl<E> ProcessWorker<E> {
+    /// How long the process has to exit after the stop signal if neither a
+    /// kill timeout nor a deadline of the shutdown is set.
+    const DEFAULT_KILL_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Runs the commands created by `command`, which is called once for every
+    /// run of the worker. Stdin, stdout and stderr of the commands are
+    /// overridden.
+    #[inline]
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        command: impl Fn() -> Command + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            command: Arc::new(command),
+            stop_signal: StopSignal::default(),
+            kill_timeout: None,
+            _error: PhantomData,
+        }
+    }
+
+    /// Sends `stop_signal` instead of `SIGTERM` to shut down the process.
+    #[inline]
+    #[must_use]
+    pub const fn with_stop_signal(mut self, stop_signal: StopSignal) -> Self {
+        self.stop_signal = stop_signal;
+        self
+    }
+
+    /// Kills the process with `SIGKILL` if it does not exit within
+    /// `kill_timeout` after the stop signal is sent.
+    ///
+    /// The deadline of the shutdown is used if it is not set, see
+    /// [`ShutdownSignal::deadline`], or 10 seconds if there is none either.
+    #[inline]
+    #[must_use]
+    pub const fn with_kill_timeout(mut self, kill_timeout: Duration) -> Self {
+        self.kill_timeout = Some(kill_timeout);
+        self
+    }
+
+    /// Sends the stop signal to `child` and waits for it to exit, it is
+    /// killed once the kill timeout or else `deadline` passes.
+    async fn stop(&self, mut child: Child, deadline: Option<Instant>) -> io::Result<()> {
+        let name = &self.name;
+        let deadline = match (self.kill_timeout, deadline) {
+            (Some(kill_timeout), _) => Instant::now() + kill_timeout,
+            (None, Some(deadline)) => deadline,
+            (None, None) => Instant::now() + Self::DEFAULT_KILL_TIMEOUT,
+        };
+        // the process is not reaped yet unless it has no id
+        if let Some(pid) = child.id() {
+            tracing::info!("Send {} to process {pid} of worker {name}", self.stop_signal);
+            if let Err(err) = self.stop_signal.send(pid) {
+                tracing::warn!(
+                    "Failed to send {} to process {pid}, error: {err}",
+                    self.stop_signal
+                );
+            }
+        }
+        let status = tokio::select! {
+            status = child.wait() => status?,
+            () = tokio::time::sleep_until(deadline) => {
+                tracing::warn!("Process of worker {name} is not stopped in time, kill it");
+                child.start_kill()?;
+                child.wait().await?
+            }
+        };
+        tracing::info!("Process of worker {name} is stopped, {status}");
+        Ok(())
+    }
+}
+
+impl<E> Clone for ProcessWorker<E> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            command: self.command.clone(),
+            stop_signal: self.stop_signal,
+            kill_timeout: self.kill_timeout,
+            _error: PhantomData,
+        }
+    }
+}
+
+impl<E> fmt::Debug for ProcessWorker<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessWorker")
+            .field("name", &self.name)
+            .field("stop_signal", &self.stop_signal)
+            .field("kill_timeout", &self.kill_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<E> Worker for ProcessWorker<E>
+where
+    E: From<Error> + Send,
+{
+    type Error = E;
+
+    fn name(&self) -> &str { &self.name }
+
+    async fn serve(self, mut shutdown_signal: ShutdownSignal) -> Result<(), Self::Error> {
+        let worker = &self.name;
+        let mut command = (self.command)();
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.spawn().context(SpawnProcessSnafu { worker })?;
+        tracing::info!("Worker {worker} spawned process {}", child.id().unwrap_or_default());
+
+        // the forwarders stop at the end of the output, even if the worker is aborted
+        if let Some(stdout) = child.stdout.take() {
+            let worker = worker.clone();
+            tokio::spawn(forward_lines(
+                stdout,
+                move |line| tracing::info!(worker = %worker, "{line}"),
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let worker = worker.clone();
+            tokio::spawn(forward_lines(
+                stderr,
+                move |line| tracing::warn!(worker = %worker, "{line}"),
+            ));
+        }
+
+        tokio::select! {
+            status = child.wait() => {
+                let status = status.context(WaitProcessSnafu { worker })?;
+                if !status.success() {
+                    return Err(ProcessExitedSnafu { worker, status }.build().into());
+                }
+                tracing::info!("Process of worker {worker} exited, {status}");
+            }
+            _ = &mut shutdown_signal => {
+                let deadline = shutdown_signal.deadline();
+                self.stop(child, deadline).await.context(WaitProcessSnafu { worker })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StopSignal {
+    fn send(self, pid: u32) -> io::Result<()> {
+        let signal = match self {
+            Self::Terminate => libc::SIGTERM,
+            Self::Interrupt => libc::SIGINT,
+            Self::Quit => libc::SIGQUIT,
+            Self::Hangup => libc::SIGHUP,
+            Self::UserDefined1 => libc::SIGUSR1,
+            Self::UserDefined2 => libc::SIGUSR2,
+        };
+        // `io::Error::other` needs a newer toolchain than the one of CI
+        #[allow(unknown_lints, clippy::io_other_error)]
+        let pid =
+            libc::pid_t::try_from(pid).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        // SAFETY: `kill` does not access memory of this process
+        if unsafe { libc::kill(pid, signal) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StopSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Terminate => "SIGTERM",
+            Self::Interrupt => "SIGINT",
+            Self::Quit => "SIGQUIT",
+            Self::Hangup => "SIGHUP",
+            Self::UserDefined1 => "SIGUSR1",
+            Self::UserDefined2 => "SIGUSR2",
+        })
+    }
+}
+
+/// Logs every line read from `output`, lines which are not valid UTF-8 are
+/// logged lossily.
+async fn forward_lines(output: impl AsyncRead + Unpin, log: impl Fn(&str)) {
+    let mut lines = BufReader::new(output).split(b'\n');
+    loop {
+        match lines.next_segment().await {
+            Ok(Some(line)) => log(String::from_utf8_lossy(&line).trim_end_matches('\r')),
+            Ok(None) => break,
+            Err(err) => {
+                tracing::warn!("Failed to read output of process, error: {err}");
+                break;
+            }
+        }
+    }
+}
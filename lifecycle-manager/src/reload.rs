@@ -0,0 +1,84 @@
+use std::{fmt, io};
+
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::sync::broadcast;
+
+/// A signal from the operating system which does not shut down the lifecycle
+/// manager, e.g. for reloading configuration or rotating logs.
+///
+/// Reload signals are only listened for if they are registered with
+/// [`with_reload_signal`](crate::LifecycleManager::with_reload_signal).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ReloadSignal {
+    /// `SIGHUP`
+    Hangup,
+
+    /// `SIGUSR1`
+    UserDefined1,
+
+    /// `SIGUSR2`
+    UserDefined2,
+}
+
+/// Receives the reload signals of a lifecycle manager, created with
+/// [`LifecycleManager::reload_receiver`](crate::LifecycleManager::reload_receiver).
+#[derive(Debug)]
+pub struct ReloadReceiver {
+    reload_rx: broadcast::Receiver<ReloadSignal>,
+}
+
+impl ReloadReceiver {
+    pub(crate) const fn new(reload_rx: broadcast::Receiver<ReloadSignal>) -> Self {
+        Self { reload_rx }
+    }
+
+    /// Waits for the next reload signal, returns `None` once the lifecycle
+    /// manager is stopped.
+    pub async fn recv(&mut self) -> Option<ReloadSignal> {
+        loop {
+            match self.reload_rx.recv().await {
+                Ok(signal) => return Some(signal),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Skipped {skipped} reload signals");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Clone for ReloadReceiver {
+    fn clone(&self) -> Self { Self { reload_rx: self.reload_rx.resubscribe() } }
+}
+
+impl fmt::Display for ReloadSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hangup => "hangup signal",
+            Self::UserDefined1 => "user-defined signal 1",
+            Self::UserDefined2 => "user-defined signal 2",
+        })
+    }
+}
+
+pub(crate) fn reload_signals(
+    signals: &[ReloadSignal],
+) -> io::Result<BoxStream<'static, ReloadSignal>> {
+    use tokio::signal::unix::{signal, SignalKind};
+    use tokio_stream::wrappers::SignalStream;
+
+    let streams = signals
+        .iter()
+        .map(|&reload_signal| {
+            let kind = match reload_signal {
+                ReloadSignal::Hangup => SignalKind::hangup(),
+                ReloadSignal::UserDefined1 => SignalKind::user_defined1(),
+                ReloadSignal::UserDefined2 => SignalKind::user_defined2(),
+            };
+            Ok(SignalStream::new(signal(kind)?).map(move |()| reload_signal))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(stream::select_all(streams).boxed())
+}
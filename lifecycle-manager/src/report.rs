@@ -0,0 +1,165 @@
+use std::{
+    any::Any,
+    fmt,
+    process::{ExitCode, Termination},
+    time::Duration,
+};
+
+use crate::ShutdownReason;
+
+/// What happened to the workers of a
+/// [`LifecycleManager`](crate::LifecycleManager), returned by
+/// [`serve`](crate::LifecycleManager::serve).
+#[derive(Debug)]
+#[must_use]
+pub struct LifecycleReport<E> {
+    /// Reports of all workers, in the order the workers were added.
+    pub workers: Vec<WorkerReport<E>>,
+
+    /// Why the lifecycle manager shut down, or `None` if all workers stopped
+    /// on their own.
+    pub shutdown_reason: Option<ShutdownReason>,
+}
+
+#[derive(Debug)]
+pub struct WorkerReport<E> {
+    pub name: String,
+    pub outcome: WorkerOutcome<E>,
+    /// How long the worker was running, including restarts.
+    pub runtime: Duration,
+    /// How many connections tracked with a
+    /// [`DrainTracker`](crate::DrainTracker) of the worker are still in
+    /// flight after all workers are stopped.
+    pub in_flight: usize,
+}
+
+#[derive(Debug)]
+pub enum WorkerOutcome<E> {
+    /// The worker stopped without error.
+    Ok,
+
+    /// The worker returned an error.
+    Error(E),
+
+    /// The worker panicked, with the panic message if it is a string.
+    Panicked(String),
+
+    /// The worker was aborted.
+    Cancelled,
+
+    /// The worker did not stop in time after it was signalled and was
+    /// aborted.
+    TimedOut,
+
+    /// The worker was never spawned, because the lifecycle manager shut down
+    /// before its dependencies were started.
+    NotStarted,
+
+    /// The worker was never spawned, because the dependency stopped before it
+    /// was started.
+    DependencyFailed { dependency: String },
+}
+
+impl<E> LifecycleReport<E> {
+    /// Returns `true` if every worker stopped without error and the
+    /// [`CircuitBreaker`](crate::CircuitBreaker) is not tripped.
+    #[inline]
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none() && !self.circuit_breaker_tripped()
+    }
+
+    /// Returns `true` if the lifecycle manager shut down because the
+    /// [`CircuitBreaker`](crate::CircuitBreaker) is tripped.
+    #[inline]
+    #[must_use]
+    pub const fn circuit_breaker_tripped(&self) -> bool {
+        matches!(self.shutdown_reason, Some(ShutdownReason::CircuitBreakerTripped { .. }))
+    }
+
+    /// Returns the reports of the workers which did not stop without error.
+    #[inline]
+    pub fn failures(&self) -> impl Iterator<Item = &WorkerReport<E>> {
+        self.workers.iter().filter(|worker| !worker.outcome.is_ok())
+    }
+
+    /// Returns the reports of the workers which panicked and were not
+    /// restarted.
+    #[inline]
+    pub fn panicked(&self) -> impl Iterator<Item = &WorkerReport<E>> {
+        self.workers.iter().filter(|worker| matches!(worker.outcome, WorkerOutcome::Panicked(_)))
+    }
+
+    /// Returns the exit code of the process, a failure unless the report
+    /// [is a success](Self::is_success).
+    #[inline]
+    #[must_use]
+    pub fn exit_code(&self) -> ExitCode {
+        if self.is_success() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+
+    /// Returns the reports of the workers which were aborted.
+    #[inline]
+    pub fn cancelled(&self) -> impl Iterator<Item = &WorkerReport<E>> {
+        self.workers.iter().filter(|worker| matches!(worker.outcome, WorkerOutcome::Cancelled))
+    }
+}
+
+impl<E> Termination for LifecycleReport<E> {
+    #[inline]
+    fn report(self) -> ExitCode { self.exit_code() }
+}
+
+impl<E> WorkerOutcome<E> {
+    #[inline]
+    #[must_use]
+    pub const fn is_ok(&self) -> bool { matches!(self, Self::Ok) }
+
+    /// Returns the kind of this outcome without its details, e.g. for
+    /// labelling metrics: `ok`, `error`, `panicked`, `cancelled`, `timed_out`,
+    /// `not_started` or `dependency_failed`.
+    #[inline]
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Error(_) => "error",
+            Self::Panicked(_) => "panicked",
+            Self::Cancelled => "cancelled",
+            Self::TimedOut => "timed_out",
+            Self::NotStarted => "not_started",
+            Self::DependencyFailed { .. } => "dependency_failed",
+        }
+    }
+}
+
+impl<E> fmt::Display for WorkerOutcome<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => f.write_str("ok"),
+            Self::Error(err) => write!(f, "error: {err}"),
+            Self::Panicked(message) => write!(f, "panicked: {message}"),
+            Self::Cancelled => f.write_str("cancelled"),
+            Self::TimedOut => f.write_str("timed out"),
+            Self::NotStarted => f.write_str("not started"),
+            Self::DependencyFailed { dependency } => {
+                write!(f, "not started, dependency {dependency} stopped before it was started")
+            }
+        }
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
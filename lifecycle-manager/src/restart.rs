@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+/// Decides whether a worker is restarted after it stops while the lifecycle
+/// manager is not shutting it down.
+///
+/// Only workers added with a factory, e.g. with
+/// [`LifecycleManager::add_restartable_worker`](crate::LifecycleManager::add_restartable_worker),
+/// can be restarted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RestartPolicy {
+    strategy: Strategy,
+    max_restarts: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Strategy {
+    #[default]
+    Never,
+    Always,
+    OnFailure,
+    ExponentialBackoff {
+        initial_delay: Duration,
+        max_delay: Duration,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum RestartDecision {
+    Stop,
+    Restart(Duration),
+    Escalate,
+}
+
+impl RestartPolicy {
+    /// Never restart the worker.
+    #[inline]
+    #[must_use]
+    pub const fn never() -> Self { Self { strategy: Strategy::Never, max_restarts: None } }
+
+    /// Restart the worker immediately whenever it stops.
+    #[inline]
+    #[must_use]
+    pub const fn always() -> Self { Self { strategy: Strategy::Always, max_restarts: None } }
+
+    /// Restart the worker immediately when it returns an error.
+    #[inline]
+    #[must_use]
+    pub const fn on_failure() -> Self { Self { strategy: Strategy::OnFailure, max_restarts: None } }
+
+    /// Restart the worker when it returns an error, waiting `initial_delay`
+    /// before the first restart and doubling the delay on every further
+    /// restart up to `max_delay`.
+    #[inline]
+    #[must_use]
+    pub const fn exponential_backoff(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            strategy: Strategy::ExponentialBackoff { initial_delay, max_delay },
+            max_restarts: None,
+        }
+    }
+
+    /// Limits how often the worker is restarted. Once the limit is exceeded,
+    /// the whole lifecycle manager is shut down.
+    #[inline]
+    #[must_use]
+    pub const fn with_max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = Some(max_restarts);
+        self
+    }
+
+    pub(crate) fn decide(&self, failed: bool, restarts: u32) -> RestartDecision {
+        let delay = match self.strategy {
+            Strategy::Never => return RestartDecision::Stop,
+            Strategy::Always => Duration::ZERO,
+            Strategy::OnFailure | Strategy::ExponentialBackoff { .. } if !failed => {
+                return RestartDecision::Stop;
+            }
+            Strategy::OnFailure => Duration::ZERO,
+            Strategy::ExponentialBackoff { initial_delay, max_delay } => {
+                initial_delay.saturating_mul(2_u32.saturating_pow(restarts)).min(max_delay)
+            }
+        };
+
+        match self.max_restarts {
+            Some(max_restarts) if restarts >= max_restarts => RestartDecision::Escalate,
+            _ => RestartDecision::Restart(delay),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{RestartDecision, RestartPolicy};
+
+    #[test]
+    fn test_never() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy, RestartPolicy::never());
+        assert_eq!(policy.decide(true, 0), RestartDecision::Stop);
+        assert_eq!(policy.decide(false, 0), RestartDecision::Stop);
+    }
+
+    #[test]
+    fn test_always() {
+        let policy = RestartPolicy::always().with_max_restarts(1);
+        assert_eq!(policy.decide(false, 0), RestartDecision::Restart(Duration::ZERO));
+        assert_eq!(policy.decide(true, 0), RestartDecision::Restart(Duration::ZERO));
+        assert_eq!(policy.decide(false, 1), RestartDecision::Escalate);
+    }
+
+    #[test]
+    fn test_on_failure() {
+        let policy = RestartPolicy::on_failure();
+        assert_eq!(policy.decide(false, 0), RestartDecision::Stop);
+        assert_eq!(policy.decide(true, 100), RestartDecision::Restart(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy =
+            RestartPolicy::exponential_backoff(Duration::from_secs(1), Duration::from_secs(10))
+                .with_max_restarts(5);
+        assert_eq!(policy.decide(false, 0), RestartDecision::Stop);
+        assert_eq!(policy.decide(true, 0), RestartDecision::Restart(Duration::from_secs(1)));
+        assert_eq!(policy.decide(true, 1), RestartDecision::Restart(Duration::from_secs(2)));
+        assert_eq!(policy.decide(true, 3), RestartDecision::Restart(Duration::from_secs(8)));
+        assert_eq!(policy.decide(true, 4), RestartDecision::Restart(Duration::from_secs(10)));
+        assert_eq!(policy.decide(true, 5), RestartDecision::Escalate);
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+/// Options of the runtime owned by
+/// [`LifecycleManager::run_blocking_with_options`](crate::LifecycleManager::run_blocking_with_options).
+///
+/// They do not cover tracing, the subscriber is installed by the caller.
+#[derive(Clone, Debug)]
+pub struct RuntimeOptions {
+    worker_threads: Option<usize>,
+    thread_name: Option<String>,
+    shutdown_timeout: Duration,
+}
+
+impl Default for RuntimeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self { worker_threads: None, thread_name: None, shutdown_timeout: Duration::from_secs(5) }
+    }
+}
+
+impl RuntimeOptions {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the number of worker threads of the runtime, which defaults to the
+    /// number of CPU cores.
+    ///
+    /// # Panics
+    ///
+    /// If `worker_threads` is zero.
+    #[inline]
+    #[must_use]
+    pub fn with_worker_threads(mut self, worker_threads: usize) -> Self {
+        assert!(worker_threads > 0, "worker threads must be positive");
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.thread_name = Some(thread_name.into());
+        self
+    }
+
+    /// Sets how long to wait for the tasks left after all workers are stopped,
+    /// e.g. blocking tasks which are still running, before returning anyway.
+    #[inline]
+    #[must_use]
+    pub const fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    pub(crate) fn build(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        builder.build()
+    }
+
+    pub(crate) const fn shutdown_timeout(&self) -> Duration { self.shutdown_timeout }
+}
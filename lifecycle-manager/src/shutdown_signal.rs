@@ -0,0 +1,273 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{mpsc, watch},
+    time::Instant,
+};
+
+use crate::DrainTracker;
+
+/// Why the lifecycle manager is shutting down.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ShutdownReason {
+    /// A signal is received from the operating system.
+    Signal(Signal),
+
+    /// The future set with
+    /// [`with_custom_shutdown`](crate::LifecycleManager::with_custom_shutdown)
+    /// is completed.
+    Custom,
+
+    /// A worker failed, either it is critical and the lifecycle manager fails
+    /// fast, or it exceeded its restart limit.
+    WorkerFailed { worker: String },
+
+    /// The lifecycle manager is dropped before it sent a shutdown signal.
+    Dropped,
+
+    /// The group of the worker is stopped with
+    /// [`WorkerGroup::stop`](crate::WorkerGroup::stop).
+    GroupStopped { group: String },
+
+    /// A worker could not be spawned because one of its dependencies stopped
+    /// before it was started.
+    StartupFailed { worker: String, dependency: String },
+
+    /// More than `max_failures` worker failures occurred within `window`, see
+    /// [`CircuitBreaker`](crate::CircuitBreaker).
+    CircuitBreakerTripped { max_failures: u32, window: Duration },
+}
+
+/// A signal from the operating system which shuts down the lifecycle manager.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Signal {
+    /// `SIGINT` on unix, `CTRL_C` on windows.
+    Interrupt,
+
+    /// `SIGTERM` on unix.
+    Terminate,
+
+    /// `CTRL_CLOSE` on windows.
+    #[cfg(windows)]
+    CtrlClose,
+
+    /// `CTRL_SHUTDOWN` on windows.
+    #[cfg(windows)]
+    CtrlShutdown,
+}
+
+/// A shutdown sent to workers.
+#[derive(Clone, Debug)]
+pub(crate) struct Shutdown {
+    pub reason: ShutdownReason,
+    pub deadline: Option<Instant>,
+    /// When the first shutdown signal was received, before the
+    /// [pre-shutdown delay](crate::LifecycleManager::with_pre_shutdown_delay),
+    /// or when the group of the worker is stopped.
+    pub started_at: Instant,
+    /// Whether workers which are not stopped should be aborted.
+    pub escalated: bool,
+    /// The code the process exits with once the lifecycle manager is stopped,
+    /// see [`EscalationPolicy::Exit`](crate::EscalationPolicy::Exit).
+    pub exit_code: Option<i32>,
+}
+
+impl Shutdown {
+    pub const fn new(reason: ShutdownReason, started_at: Instant) -> Self {
+        Self { reason, deadline: None, started_at, escalated: false, exit_code: None }
+    }
+}
+
+/// Tells the supervisor that the worker at `index` is started.
+#[derive(Clone, Debug)]
+pub(crate) struct StartedNotifier {
+    pub index: usize,
+    pub started_tx: mpsc::UnboundedSender<usize>,
+}
+
+/// A future which resolves once the worker should shut down, with the reason
+/// of the shutdown.
+pub struct ShutdownSignal {
+    name: String,
+    shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    started_notifier: Option<StartedNotifier>,
+    /// The connections in flight of all drain trackers of the worker.
+    in_flight: Arc<watch::Sender<usize>>,
+    future: Pin<Box<dyn Future<Output = ShutdownReason> + Send + Sync + 'static>>,
+}
+
+impl ShutdownSignal {
+    pub(crate) fn new(name: String, shutdown_rx: watch::Receiver<Option<Shutdown>>) -> Self {
+        let mut rx = shutdown_rx.clone();
+        let worker = name.clone();
+        let future = async move {
+            let reason = loop {
+                if let Some(shutdown) = rx.borrow_and_update().as_ref() {
+                    break shutdown.reason.clone();
+                }
+                if rx.changed().await.is_err() {
+                    break ShutdownReason::Dropped;
+                }
+            };
+            tracing::info!(
+                "Shutdown signal received ({reason}), try to shutdown worker `{worker}`"
+            );
+            reason
+        };
+
+        Self {
+            name,
+            shutdown_rx,
+            started_notifier: None,
+            in_flight: Arc::new(watch::channel(0).0),
+            future: Box::pin(future),
+        }
+    }
+
+    pub(crate) fn with_started_notifier(mut self, started_notifier: StartedNotifier) -> Self {
+        self.started_notifier = Some(started_notifier);
+        self
+    }
+
+    pub(crate) fn with_in_flight(mut self, in_flight: Arc<watch::Sender<usize>>) -> Self {
+        self.in_flight = in_flight;
+        self
+    }
+
+    /// Creates another shutdown signal resolving together with this one.
+    pub(crate) fn subscribe(&self, name: &str) -> Self {
+        Self::new(name.to_string(), self.shutdown_rx.clone()).with_in_flight(self.in_flight.clone())
+    }
+
+    /// Creates a shutdown signal for a part of the worker which is signalled
+    /// through `shutdown_rx` instead, e.g. a single run of a periodic worker.
+    #[cfg(feature = "periodic")]
+    pub(crate) fn subscribe_to(
+        &self,
+        name: &str,
+        shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    ) -> Self {
+        Self::new(name.to_string(), shutdown_rx).with_in_flight(self.in_flight.clone())
+    }
+
+    #[cfg(feature = "periodic")]
+    pub(crate) fn shutdown_rx(&self) -> watch::Receiver<Option<Shutdown>> {
+        self.shutdown_rx.clone()
+    }
+
+    /// Creates a shutdown signal for another run of the same worker.
+    pub(crate) fn renew(&self) -> Self {
+        let mut shutdown_signal = self.subscribe(&self.name);
+        shutdown_signal.started_notifier = self.started_notifier.clone();
+        shutdown_signal
+    }
+
+    /// Creates a [`DrainTracker`] for the connections of this worker.
+    #[inline]
+    #[must_use]
+    pub fn drain_tracker(&self) -> DrainTracker {
+        DrainTracker::new(self.name.clone(), self.shutdown_rx.clone(), self.in_flight.clone())
+    }
+
+    /// Reports that the worker is started. Workers depending on a worker
+    /// added with
+    /// [`with_started_notification`](crate::WorkerOptions::with_started_notification)
+    /// are only spawned after it calls this.
+    #[inline]
+    pub fn notify_started(&self) {
+        if let Some(StartedNotifier { index, started_tx }) = &self.started_notifier {
+            let _unused = started_tx.send(*index);
+        }
+    }
+
+    /// Returns the reason of the shutdown, or `None` if the worker is not
+    /// signalled yet.
+    #[inline]
+    #[must_use]
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_rx.borrow().as_ref().map(|shutdown| shutdown.reason.clone())
+    }
+
+    /// Returns the instant by which the worker should be stopped, or `None` if
+    /// the worker is not signalled yet or there is no such deadline, e.g. when
+    /// its group is stopped and it has no shutdown timeout.
+    ///
+    /// It is the earlier of the
+    /// [timeout](crate::LifecycleManager::with_timeout) of the lifecycle
+    /// manager and the
+    /// [shutdown timeout](crate::WorkerOptions::with_shutdown_timeout) of the
+    /// worker, counted from the first shutdown signal. A worker is aborted
+    /// once its own shutdown timeout passes, or once the deadline passes
+    /// after another shutdown signal is received, see
+    /// [`EscalationPolicy`](crate::EscalationPolicy).
+    #[inline]
+    #[must_use]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.shutdown_rx.borrow().as_ref().and_then(|shutdown| shutdown.deadline)
+    }
+}
+
+impl Future for ShutdownSignal {
+    type Output = ShutdownReason;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSignal")
+            .field("reason", &self.reason())
+            .field("deadline", &self.deadline())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Signal(signal) => write!(f, "received {signal}"),
+            Self::Custom => f.write_str("custom shutdown"),
+            Self::WorkerFailed { worker } => write!(f, "worker {worker} failed"),
+            Self::Dropped => f.write_str("lifecycle manager is dropped"),
+            Self::GroupStopped { group } => write!(f, "group {group} is stopped"),
+            Self::StartupFailed { worker, dependency } => {
+                write!(
+                    f,
+                    "worker {worker} could not start, {dependency} stopped before it was started"
+                )
+            }
+            Self::CircuitBreakerTripped { max_failures, window } => {
+                write!(
+                    f,
+                    "circuit breaker tripped, more than {max_failures} worker failures within \
+                     {window:?}"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Interrupt => "interrupt signal",
+            Self::Terminate => "terminate signal",
+            #[cfg(windows)]
+            Self::CtrlClose => "close signal",
+            #[cfg(windows)]
+            Self::CtrlShutdown => "shutdown signal",
+        })
+    }
+}
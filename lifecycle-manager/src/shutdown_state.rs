@@ -1,8 +1,18 @@
+/// The state of a [`SignalWatcher`](crate::SignalWatcher), advanced by every
+/// shutdown signal.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum ShutdownState {
+    /// The signal watcher is not running yet.
     Initial,
+
+    /// No shutdown signal is received yet.
     WaitForSignal,
+
+    /// The first shutdown signal is received, workers are shutting down.
     ShuttingDown,
+
+    /// Another shutdown signal is received, the shutdown is escalated after
+    /// the timeout.
     Aborting,
 }
 
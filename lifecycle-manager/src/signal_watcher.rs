@@ -1,13 +1,27 @@
-use std::{io, time::Duration};
+use std::{future::Future, io, sync::Arc, time::Duration};
 
 use futures::{
     future::FutureExt,
     stream,
     stream::{BoxStream, StreamExt},
 };
-use tokio::{sync::watch, task::JoinHandle};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{
+    events::{self, LifecycleEvent},
+    metrics::Metrics,
+    shutdown_signal::{Shutdown, Signal},
+    EscalationPolicy, LifecycleEvents, MetricsRecorder, ShutdownReason, ShutdownSignal,
+    ShutdownState,
+};
+#[cfg(unix)]
+use crate::{reload, ReloadReceiver, ReloadSignal};
 
-use crate::{ShutdownSignal, ShutdownState};
+type PreShutdownHook = Box<dyn Fn(&ShutdownReason) + Send>;
 
 #[derive(Debug)]
 pub struct SignalWatcher {
@@ -18,8 +32,31 @@ impl SignalWatcher {
     #[inline]
     #[must_use]
     pub fn builder() -> Builder {
-        let (shutdown_tx, shutdown_rx) = watch::channel(());
-        Builder { shutdown_tx, shutdown_rx, shutdown_signal: None, timeout: None }
+        let (shutdown_tx, shutdown_rx) = watch::channel(None);
+        let (trigger_tx, trigger_rx) = mpsc::unbounded_channel();
+        let (state_tx, _) = watch::channel(ShutdownState::default());
+        let (event_tx, _) = broadcast::channel(64);
+        #[cfg(unix)]
+        let (reload_tx, _) = broadcast::channel(16);
+        Builder {
+            shutdown_tx,
+            shutdown_rx,
+            trigger_tx,
+            trigger_rx,
+            state_tx,
+            event_tx,
+            pre_shutdown_hooks: Vec::new(),
+            metrics: Metrics::default(),
+            #[cfg(unix)]
+            reload_tx,
+            #[cfg(unix)]
+            reload_signals: Vec::new(),
+            shutdown_signal: None,
+            signal_source: None,
+            timeout: None,
+            pre_shutdown_delay: None,
+            escalation_policy: EscalationPolicy::default(),
+        }
     }
 
     #[inline]
@@ -35,44 +72,142 @@ impl SignalWatcher {
 }
 
 pub struct Builder {
-    shutdown_tx: watch::Sender<()>,
-    shutdown_rx: watch::Receiver<()>,
-    shutdown_signal: Option<ShutdownSignal>,
+    shutdown_tx: watch::Sender<Option<Shutdown>>,
+    shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    trigger_tx: mpsc::UnboundedSender<ShutdownReason>,
+    trigger_rx: mpsc::UnboundedReceiver<ShutdownReason>,
+    state_tx: watch::Sender<ShutdownState>,
+    event_tx: broadcast::Sender<LifecycleEvent>,
+    pre_shutdown_hooks: Vec<PreShutdownHook>,
+    metrics: Metrics,
+    #[cfg(unix)]
+    reload_tx: broadcast::Sender<ReloadSignal>,
+    #[cfg(unix)]
+    reload_signals: Vec<ReloadSignal>,
+    shutdown_signal: Option<BoxStream<'static, ShutdownReason>>,
+    /// Replaces the signals of the operating system, see
+    /// [`testing::SignalSource`](crate::testing::SignalSource).
+    signal_source: Option<BoxStream<'static, ReceivedSignal>>,
     timeout: Option<Duration>,
+    pre_shutdown_delay: Option<Duration>,
+    escalation_policy: EscalationPolicy,
 }
 
 impl Builder {
+    /// Sets how long workers have to stop after they are signalled, 10
+    /// seconds by default. Workers see the deadline with
+    /// [`ShutdownSignal::deadline`], another shutdown signal escalates once it
+    /// passes, see [`EscalationPolicy`].
     #[inline]
     pub fn with_timeout(&mut self, timeout: Duration) -> &mut Self {
         self.timeout = Some(timeout);
         self
     }
 
+    /// Delays sending the shutdown signal to workers after the first shutdown
+    /// signal is received. During the delay the [`ShutdownState`] is
+    /// [`ShuttingDown`](ShutdownState::ShuttingDown) but workers keep serving,
+    /// another shutdown signal ends the delay right away.
+    #[inline]
+    pub fn with_pre_shutdown_delay(&mut self, delay: Duration) -> &mut Self {
+        self.pre_shutdown_delay = Some(delay);
+        self
+    }
+
+    /// Calls `hook` right before the shutdown signal is sent to workers.
+    #[inline]
+    pub fn with_pre_shutdown_hook(
+        &mut self,
+        hook: impl Fn(&ShutdownReason) + Send + 'static,
+    ) -> &mut Self {
+        self.pre_shutdown_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Records the metrics of the workers and the [`ShutdownState`] with
+    /// `recorder`.
     #[inline]
-    pub fn with_custom_shutdown(&mut self, shutdown_signal: ShutdownSignal) -> &mut Self {
-        self.shutdown_signal = Some(shutdown_signal);
+    pub fn with_metrics_recorder(&mut self, recorder: impl MetricsRecorder + 'static) -> &mut Self {
+        self.metrics = Metrics::new(Arc::new(recorder));
+        self
+    }
+
+    /// Sets what happens when workers do not stop in time after another
+    /// shutdown signal is received, see [`EscalationPolicy`].
+    #[inline]
+    pub fn with_escalation_policy(&mut self, escalation_policy: EscalationPolicy) -> &mut Self {
+        self.escalation_policy = escalation_policy;
+        self
+    }
+
+    #[inline]
+    pub fn with_custom_shutdown(
+        &mut self,
+        shutdown_signal: impl Future<Output = ()> + Send + 'static,
+    ) -> &mut Self {
+        self.shutdown_signal =
+            Some(shutdown_signal.map(|()| ShutdownReason::Custom).into_stream().boxed());
+        self
+    }
+
+    /// Listens for `signal` and sends it to the [`ReloadReceiver`]s instead of
+    /// shutting down.
+    #[cfg(unix)]
+    #[inline]
+    pub fn with_reload_signal(&mut self, signal: ReloadSignal) -> &mut Self {
+        if !self.reload_signals.contains(&signal) {
+            self.reload_signals.push(signal);
+        }
+        self
+    }
+
+    #[cfg(unix)]
+    #[inline]
+    #[must_use]
+    pub fn reload_receiver(&self) -> ReloadReceiver {
+        ReloadReceiver::new(self.reload_tx.subscribe())
+    }
+
+    /// Receives signals from `signal_source` instead of the operating system,
+    /// which lets tests fire signals without affecting each other.
+    #[cfg(any(test, feature = "test-util"))]
+    #[inline]
+    pub fn with_signal_source(&mut self, signal_source: crate::testing::SignalSource) -> &mut Self {
+        self.signal_source = Some(signal_source.into_stream());
         self
     }
 
     #[must_use]
     pub fn create_shutdown_signal(&self, name: &str) -> ShutdownSignal {
-        let name = name.to_string();
-        let mut shutdown_rx = self.shutdown_rx.clone();
-        let fut = async move {
-            match shutdown_rx.changed().await {
-                Ok(_) => {
-                    tracing::info!("Shutdown signal received, try to shutdown worker `{}`", name);
-                }
-                Err(_) => {
-                    tracing::info!(
-                        "Shutdown signal sender is dropped, try to shutdown worker `{}`",
-                        name
-                    );
-                }
-            }
-        };
+        ShutdownSignal::new(name.to_string(), self.shutdown_rx.clone())
+    }
+
+    #[inline]
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Option<Shutdown>> { self.shutdown_rx.clone() }
+
+    /// Returns a receiver of the [`ShutdownState`], which leaves
+    /// [`WaitForSignal`](ShutdownState::WaitForSignal) as soon as the first
+    /// shutdown signal is received.
+    #[inline]
+    #[must_use]
+    pub fn shutdown_state(&self) -> watch::Receiver<ShutdownState> { self.state_tx.subscribe() }
+
+    /// Returns a receiver for the [`LifecycleEvent`]s sent from now on.
+    #[inline]
+    #[must_use]
+    pub fn events(&self) -> LifecycleEvents { LifecycleEvents::new(self.event_tx.subscribe()) }
 
-        Box::pin(fut)
+    #[inline]
+    pub(crate) fn event_sender(&self) -> broadcast::Sender<LifecycleEvent> { self.event_tx.clone() }
+
+    #[inline]
+    pub(crate) fn metrics(&self) -> Metrics { self.metrics.clone() }
+
+    /// Returns a sender for shutting down without a signal. Unlike signals, it
+    /// is ignored once a shutdown is underway.
+    #[inline]
+    pub(crate) fn shutdown_trigger(&self) -> mpsc::UnboundedSender<ShutdownReason> {
+        self.trigger_tx.clone()
     }
 
     /// # Errors
@@ -80,55 +215,180 @@ impl Builder {
     /// If [`tokio::signal::unix::signal`
     /// error](fn@tokio::signal::unix::signal#errors).
     pub fn build(self) -> io::Result<SignalWatcher> {
-        let (shutdown_tx, internal_shutdown_signal, shutdown_timeout) = {
+        let (
+            shutdown_tx,
+            state_tx,
+            event_tx,
+            pre_shutdown_hooks,
+            metrics,
+            mut trigger_rx,
+            internal_shutdown_signal,
+            shutdown_timeout,
+            pre_shutdown_delay,
+            escalation_policy,
+        ) = {
             (
                 self.shutdown_tx,
+                self.state_tx,
+                self.event_tx,
+                self.pre_shutdown_hooks,
+                self.metrics,
+                self.trigger_rx,
                 self.shutdown_signal,
                 self.timeout.unwrap_or_else(|| Duration::from_secs(10)),
+                self.pre_shutdown_delay.filter(|delay| !delay.is_zero()),
+                self.escalation_policy,
             )
         };
 
+        // shutdown and reload signals share one stream to be handled in order
         let mut signal_stream = {
-            let mut streams = shutdown_signals()?;
+            let mut streams = match self.signal_source {
+                Some(signal_source) => vec![signal_source],
+                None => {
+                    let mut streams = shutdown_signals()?
+                        .into_iter()
+                        .map(|stream| stream.map(ReceivedSignal::Shutdown).boxed())
+                        .collect::<Vec<_>>();
+                    #[cfg(unix)]
+                    streams.push(
+                        reload::reload_signals(&self.reload_signals)?
+                            .map(ReceivedSignal::Reload)
+                            .boxed(),
+                    );
+                    streams
+                }
+            };
 
             if let Some(shutdown_signal) = internal_shutdown_signal {
-                streams.push(shutdown_signal.into_stream().boxed());
+                streams.push(shutdown_signal.map(ReceivedSignal::Shutdown).boxed());
             }
 
             stream::select_all(streams)
         };
 
+        #[cfg(unix)]
+        let (reload_tx, reload_signals) = (self.reload_tx, self.reload_signals);
+
         let join_handle = tokio::spawn(async move {
             let mut state = ShutdownState::default();
-            tracing::info!("SignalWorker is waiting for signals");
+            let mut escalation_deadline = None;
+            // the reason and the instant of the first shutdown signal
+            let mut pending_shutdown: Option<(ShutdownReason, Instant)> = None;
+            let mut state_entered_at = Instant::now();
+            let mut send_state = |previous: ShutdownState, state: ShutdownState| {
+                if previous == state {
+                    return;
+                }
+                state_tx.send_replace(state);
+                metrics.record(|recorder| {
+                    recorder.set_shutdown_state(state, previous, state_entered_at.elapsed());
+                });
+                state_entered_at = Instant::now();
+                events::emit(&event_tx, LifecycleEvent::ShutdownStateChanged { state });
+            };
 
-            while signal_stream.next().await.is_some() {
-                match state.next() {
-                    None | Some(ShutdownState::Initial) => unreachable!(),
-                    Some(ShutdownState::WaitForSignal) => {
-                        tracing::info!("Send shutdown signal to all workers");
+            state.next();
+            send_state(ShutdownState::Initial, state);
+            tracing::info!("SignalWorker is waiting for signals");
 
-                        if let Err(_err) = shutdown_tx.send(()) {
-                            tracing::warn!("Failed to send shutdown signal");
+            loop {
+                let reason = tokio::select! {
+                    Some(signal) = signal_stream.next() => match signal {
+                        ReceivedSignal::Shutdown(reason) => reason,
+                        #[cfg(unix)]
+                        ReceivedSignal::Reload(signal) => {
+                            // only a signal source sends signals which are not registered
+                            if !reload_signals.contains(&signal) {
+                                tracing::warn!("Ignore {signal}, it is not a reload signal");
+                                continue;
+                            }
+                            tracing::info!("Send {signal} to reload receivers");
+                            let _unused = reload_tx.send(signal);
+                            events::emit(&event_tx, LifecycleEvent::ReloadSignalReceived { signal });
+                            continue;
+                        }
+                    },
+                    Some(reason) = trigger_rx.recv() => {
+                        if state != ShutdownState::WaitForSignal {
+                            continue;
                         }
+                        reason
                     }
-                    Some(ShutdownState::ShuttingDown) => {
+                    () = tokio::time::sleep_until(
+                        pending_shutdown.as_ref().map_or_else(Instant::now, |(_, received_at)| {
+                            *received_at + pre_shutdown_delay.unwrap_or_default()
+                        })
+                    ), if pending_shutdown.is_some() => {
+                        if let Some((reason, received_at)) = pending_shutdown.take() {
+                            let shutdown = Shutdown::new(reason, received_at);
+                            send_shutdown(&shutdown_tx, &pre_shutdown_hooks, shutdown, shutdown_timeout);
+                        }
+                        continue;
+                    }
+                    () = tokio::time::sleep_until(escalation_deadline.unwrap_or_else(Instant::now)),
+                        if escalation_deadline.is_some() =>
+                    {
+                        escalation_deadline = None;
                         tracing::warn!(
-                            "Another shutdown signal is received, force exit in {} milliseconds",
+                            "Workers are not stopped in {} milliseconds, escalate",
                             shutdown_timeout.as_millis()
                         );
-
-                        tokio::spawn(async move {
-                            tokio::time::sleep(shutdown_timeout).await;
-                            tracing::warn!("Force exit this process");
-                            std::process::exit(1);
-                        });
+                        escalation_policy.escalate(&shutdown_tx);
+                        continue;
                     }
+                    else => break,
+                };
+                events::emit(&event_tx, LifecycleEvent::SignalReceived { reason: reason.clone() });
+
+                if let Some((reason, received_at)) = pending_shutdown.take() {
+                    tracing::info!(
+                        "Another shutdown signal is received, skip the pre-shutdown delay"
+                    );
+                    let shutdown = Shutdown::new(reason, received_at);
+                    send_shutdown(&shutdown_tx, &pre_shutdown_hooks, shutdown, shutdown_timeout);
+                    continue;
+                }
+
+                let previous = state;
+                let next_state = state.next();
+                send_state(previous, state);
+                match next_state {
+                    Some(ShutdownState::Initial | ShutdownState::WaitForSignal) => unreachable!(),
+                    Some(ShutdownState::ShuttingDown) => match pre_shutdown_delay {
+                        Some(delay) => {
+                            tracing::info!(
+                                "Send shutdown signal to all workers in {} milliseconds, {reason}",
+                                delay.as_millis()
+                            );
+                            pending_shutdown = Some((reason, Instant::now()));
+                        }
+                        None => send_shutdown(
+                            &shutdown_tx,
+                            &pre_shutdown_hooks,
+                            Shutdown::new(reason, Instant::now()),
+                            shutdown_timeout,
+                        ),
+                    },
                     Some(ShutdownState::Aborting) => {
-                        tracing::error!(
-                            "Could not shut down this process gracefully, abort this process"
+                        // the deadline is only missing if the workers are not signalled yet
+                        let mut deadline = Instant::now() + shutdown_timeout;
+                        shutdown_tx.send_modify(|shutdown| {
+                            if let Some(shutdown) = shutdown {
+                                deadline = shutdown.deadline.map_or(deadline, |d| d.min(deadline));
+                                shutdown.deadline = Some(deadline);
+                            }
+                        });
+                        tracing::warn!(
+                            "Another shutdown signal is received, escalate in {} milliseconds",
+                            deadline.saturating_duration_since(Instant::now()).as_millis()
                         );
-                        std::process::abort();
+                        escalation_deadline = Some(deadline);
+                    }
+                    None => {
+                        tracing::error!("Could not shut down this process gracefully, escalate");
+                        escalation_deadline = None;
+                        escalation_policy.escalate(&shutdown_tx);
                     }
                 }
             }
@@ -138,27 +398,139 @@ impl Builder {
     }
 }
 
+/// A signal received by a [`SignalWatcher`].
+pub(crate) enum ReceivedSignal {
+    Shutdown(ShutdownReason),
+    #[cfg(unix)]
+    Reload(ReloadSignal),
+}
+
+/// Signals the workers, which should be stopped within `timeout` after the
+/// shutdown is started.
+fn send_shutdown(
+    shutdown_tx: &watch::Sender<Option<Shutdown>>,
+    pre_shutdown_hooks: &[PreShutdownHook],
+    shutdown: Shutdown,
+    timeout: Duration,
+) {
+    for hook in pre_shutdown_hooks {
+        hook(&shutdown.reason);
+    }
+
+    tracing::info!("Send shutdown signal to all workers, {}", shutdown.reason);
+
+    let shutdown = Shutdown { deadline: Some(shutdown.started_at + timeout), ..shutdown };
+    if let Err(_err) = shutdown_tx.send(Some(shutdown)) {
+        tracing::warn!("Failed to send shutdown signal");
+    }
+}
+
 #[cfg(unix)]
-fn shutdown_signals() -> io::Result<Vec<BoxStream<'static, ()>>> {
+fn shutdown_signals() -> io::Result<Vec<BoxStream<'static, ShutdownReason>>> {
     use tokio::signal::unix::{signal, SignalKind};
     use tokio_stream::wrappers::SignalStream;
 
     Ok(vec![
-        SignalStream::new(signal(SignalKind::terminate())?).boxed(),
-        SignalStream::new(signal(SignalKind::interrupt())?).boxed(),
+        SignalStream::new(signal(SignalKind::terminate())?)
+            .map(|()| ShutdownReason::Signal(Signal::Terminate))
+            .boxed(),
+        SignalStream::new(signal(SignalKind::interrupt())?)
+            .map(|()| ShutdownReason::Signal(Signal::Interrupt))
+            .boxed(),
     ])
 }
 
 #[cfg(windows)]
-fn shutdown_signals() -> io::Result<Vec<BoxStream<'static, ()>>> {
+fn shutdown_signals() -> io::Result<Vec<BoxStream<'static, ShutdownReason>>> {
     use tokio::signal::windows::{ctrl_c, ctrl_close, ctrl_shutdown};
     use tokio_stream::wrappers::CtrlCStream;
 
     use crate::windows::{CtrlCloseStream, CtrlShutdownStream};
 
     Ok(vec![
-        CtrlCStream::new(ctrl_c()?).boxed(),
-        CtrlCloseStream::new(ctrl_close()?).boxed(),
-        CtrlShutdownStream::new(ctrl_shutdown()?).boxed(),
+        CtrlCStream::new(ctrl_c()?).map(|()| ShutdownReason::Signal(Signal::Interrupt)).boxed(),
+        CtrlCloseStream::new(ctrl_close()?)
+            .map(|()| ShutdownReason::Signal(Signal::CtrlClose))
+            .boxed(),
+        CtrlShutdownStream::new(ctrl_shutdown()?)
+            .map(|()| ShutdownReason::Signal(Signal::CtrlShutdown))
+            .boxed(),
     ])
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{stream, StreamExt};
+    use tokio::time::Instant;
+
+    use super::SignalWatcher;
+    use crate::{ShutdownReason, ShutdownState};
+
+    fn delayed_signals(delays: &[u64]) -> futures::stream::BoxStream<'static, ShutdownReason> {
+        stream::iter(delays.to_vec())
+            .then(|delay| async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                ShutdownReason::Custom
+            })
+            .boxed()
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_pre_shutdown_delay() {
+        let mut builder = SignalWatcher::builder();
+        builder.with_pre_shutdown_delay(Duration::from_millis(300));
+        builder.shutdown_signal = Some(delayed_signals(&[50]));
+        let shutdown_rx = builder.subscribe();
+        let shutdown_state = builder.shutdown_state();
+        let signal_watcher = builder.build().unwrap();
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(*shutdown_state.borrow(), ShutdownState::ShuttingDown);
+        assert!(shutdown_rx.borrow().is_none());
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(shutdown_rx.borrow().is_some());
+        signal_watcher.wait();
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_pre_shutdown_delay_deadline() {
+        let mut builder = SignalWatcher::builder();
+        builder
+            .with_pre_shutdown_delay(Duration::from_secs(2))
+            .with_timeout(Duration::from_secs(5));
+        builder.shutdown_signal = Some(delayed_signals(&[50]));
+        let mut shutdown_rx = builder.subscribe();
+        let started_at = Instant::now();
+        let signal_watcher = builder.build().unwrap();
+
+        shutdown_rx.wait_for(Option::is_some).await.unwrap();
+        assert_eq!(started_at.elapsed(), Duration::from_millis(2050));
+        // the delay counts against the timeout
+        let deadline = shutdown_rx.borrow().as_ref().unwrap().deadline;
+        assert_eq!(deadline, Some(started_at + Duration::from_millis(5050)));
+        signal_watcher.wait();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_pre_shutdown_delay_skipped() {
+        let mut builder = SignalWatcher::builder();
+        builder.with_pre_shutdown_delay(Duration::from_secs(60));
+        builder.shutdown_signal = Some(delayed_signals(&[50, 50]));
+        let mut shutdown_rx = builder.subscribe();
+        let shutdown_state = builder.shutdown_state();
+        let signal_watcher = builder.build().unwrap();
+
+        tokio::time::timeout(Duration::from_secs(1), shutdown_rx.wait_for(Option::is_some))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*shutdown_state.borrow(), ShutdownState::ShuttingDown);
+        signal_watcher.wait();
+    }
+}
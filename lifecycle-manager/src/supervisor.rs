@@ -0,0 +1,748 @@
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::{AbortHandle, JoinError},
+    time::Instant,
+};
+
+use crate::{
+    circuit_breaker::FailureWindow,
+    dependency_graph::DependencyGraph,
+    events::{self, LifecycleEvent},
+    group::GroupStop,
+    metrics::Metrics,
+    report::panic_message,
+    restart::RestartDecision,
+    shutdown_signal::{Shutdown, StartedNotifier},
+    worker::{WorkerEntry, WorkerFn},
+    CircuitBreaker, LifecycleReport, RestartPolicy, ShutdownReason, ShutdownSignal, WorkerOptions,
+    WorkerOutcome, WorkerReport,
+};
+
+type JoinResult<E> = (usize, Result<Result<(), E>, JoinError>);
+
+/// Decides what happens when a worker fails, i.e. it returns an error or
+/// panics, and is not restarted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FailurePolicy {
+    /// Keep the other workers running.
+    #[default]
+    Continue,
+
+    /// Shut down all workers if a
+    /// [critical](crate::WorkerOptions::with_critical) worker fails.
+    FailFast,
+}
+
+/// Spawns workers once their dependencies are started and shuts them down in
+/// dependency order.
+pub(crate) struct Supervisor<E> {
+    workers: Vec<SupervisedWorker<E>>,
+    running_workers: FuturesUnordered<BoxFuture<'static, JoinResult<E>>>,
+    worker_rx: mpsc::UnboundedReceiver<WorkerEntry<E>>,
+    stop_rx: mpsc::UnboundedReceiver<GroupStop>,
+    /// Groups being stopped, with the senders to answer once they are.
+    stopping_groups: Vec<(String, Vec<oneshot::Sender<()>>)>,
+    started_tx: mpsc::UnboundedSender<usize>,
+    started_rx: mpsc::UnboundedReceiver<usize>,
+    shutdown_trigger: mpsc::UnboundedSender<ShutdownReason>,
+    reporter: Reporter,
+    failure_policy: FailurePolicy,
+}
+
+struct SupervisedWorker<E> {
+    name: String,
+    options: WorkerOptions,
+    shutdown_tx: watch::Sender<Option<Shutdown>>,
+    /// The worker waiting for its dependencies to be started.
+    pending: Option<(WorkerFn<E>, RestartPolicy)>,
+    abort_handle: Option<AbortHandle>,
+    started_at: Instant,
+    started: bool,
+    timed_out: bool,
+    stopped: Option<(WorkerOutcome<E>, Duration)>,
+    /// The connections in flight, see [`DrainTracker`](crate::DrainTracker).
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+enum DependencyStatus {
+    Started,
+    Starting,
+    Failed(String),
+}
+
+impl<E> Supervisor<E>
+where
+    E: std::error::Error + Send + 'static,
+{
+    pub fn new(
+        worker_rx: mpsc::UnboundedReceiver<WorkerEntry<E>>,
+        stop_rx: mpsc::UnboundedReceiver<GroupStop>,
+        shutdown_trigger: mpsc::UnboundedSender<ShutdownReason>,
+        reporter: Reporter,
+        failure_policy: FailurePolicy,
+    ) -> Self {
+        let (started_tx, started_rx) = mpsc::unbounded_channel();
+        Self {
+            workers: Vec::new(),
+            running_workers: FuturesUnordered::new(),
+            worker_rx,
+            stop_rx,
+            stopping_groups: Vec::new(),
+            started_tx,
+            started_rx,
+            shutdown_trigger,
+            reporter,
+            failure_policy,
+        }
+    }
+
+    pub fn spawn(&mut self, entry: WorkerEntry<E>) -> usize {
+        let WorkerEntry { name, options, worker_fn, restartable } = entry;
+        let restart_policy = if restartable {
+            options.restart_policy()
+        } else {
+            if options.restart_policy() != RestartPolicy::never() {
+                tracing::warn!("Worker {name} is not added with a factory, it is never restarted");
+            }
+            RestartPolicy::never()
+        };
+
+        let index = self.workers.len();
+        let (shutdown_tx, _) = watch::channel(None);
+        self.workers.push(SupervisedWorker {
+            name,
+            options,
+            shutdown_tx,
+            pending: Some((worker_fn, restart_policy)),
+            abort_handle: None,
+            started_at: Instant::now(),
+            started: false,
+            timed_out: false,
+            stopped: None,
+            in_flight: Arc::new(watch::channel(0).0),
+        });
+        self.start_pending_workers();
+        index
+    }
+
+    /// Spawns the pending workers whose dependencies are started, and gives up
+    /// on the ones whose dependencies stopped before they were started.
+    fn start_pending_workers(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..self.workers.len() {
+                if self.workers[index].pending.is_none() {
+                    continue;
+                }
+                match self.dependency_status(index) {
+                    DependencyStatus::Started => self.start(index),
+                    DependencyStatus::Starting => continue,
+                    DependencyStatus::Failed(dependency) => {
+                        let worker = &mut self.workers[index];
+                        tracing::error!(
+                            "Worker {} could not start, {dependency} stopped before it was started",
+                            worker.name
+                        );
+                        worker.pending = None;
+                        let outcome =
+                            WorkerOutcome::DependencyFailed { dependency: dependency.clone() };
+                        self.reporter.stopped(worker, &outcome);
+                        worker.stopped = Some((outcome, Duration::ZERO));
+                        let _unused = self.shutdown_trigger.send(ShutdownReason::StartupFailed {
+                            worker: worker.name.clone(),
+                            dependency,
+                        });
+                    }
+                }
+                changed = true;
+            }
+        }
+    }
+
+    fn dependency_status(&self, index: usize) -> DependencyStatus {
+        for dependency in self.workers[index].options.dependencies() {
+            for worker in self.workers.iter().filter(|worker| &worker.name == dependency) {
+                if worker.started {
+                    continue;
+                }
+                if worker.stopped.is_some() {
+                    return DependencyStatus::Failed(dependency.clone());
+                }
+                return DependencyStatus::Starting;
+            }
+        }
+        DependencyStatus::Started
+    }
+
+    fn start(&mut self, index: usize) {
+        let worker = &mut self.workers[index];
+        let Some((worker_fn, restart_policy)) = worker.pending.take() else { return };
+
+        let shutdown_signal =
+            ShutdownSignal::new(worker.name.clone(), worker.shutdown_tx.subscribe())
+                .with_started_notifier(StartedNotifier {
+                    index,
+                    started_tx: self.started_tx.clone(),
+                })
+                .with_in_flight(worker.in_flight.clone());
+        let join_handle = tokio::spawn(supervise(
+            worker.name.clone(),
+            worker_fn,
+            restart_policy,
+            shutdown_signal,
+            self.shutdown_trigger.clone(),
+            self.reporter.clone(),
+        ));
+        worker.abort_handle = Some(join_handle.abort_handle());
+        worker.started_at = Instant::now();
+        worker.started = !worker.options.started_notification();
+        self.reporter.metrics.record(|recorder| recorder.set_worker_up(&worker.name, true));
+        self.reporter.emit(LifecycleEvent::WorkerSpawned { worker: worker.name.clone() });
+        if worker.started {
+            self.reporter.emit(LifecycleEvent::WorkerStarted { worker: worker.name.clone() });
+        }
+        self.running_workers.push(join_handle.map(move |result| (index, result)).boxed());
+    }
+
+    /// Runs until `shutdown_rx` is notified and all workers are stopped, or
+    /// until all workers are stopped and no more workers can be added.
+    pub async fn serve(
+        mut self,
+        mut shutdown_rx: watch::Receiver<Option<Shutdown>>,
+    ) -> LifecycleReport<E> {
+        let mut accepting = true;
+        while accepting || !self.running_workers.is_empty() {
+            let all_started = self.workers.iter().all(|worker| worker.started);
+            self.reporter
+                .workers_started
+                .send_if_modified(|started| std::mem::replace(started, all_started) != all_started);
+            // only the workers of stopped groups are signalled yet
+            let next_deadline = self.next_deadline();
+
+            tokio::select! {
+                Some((index, result)) = self.running_workers.next() => {
+                    if self.on_stopped(index, result) {
+                        let worker = self.workers[index].name.clone();
+                        let _unused =
+                            self.shutdown_trigger.send(ShutdownReason::WorkerFailed { worker });
+                    }
+                    self.start_pending_workers();
+                    self.stop_groups();
+                }
+                Some(group_stop) = self.stop_rx.recv() => self.stop_group(group_stop),
+                Some(index) = self.started_rx.recv() => {
+                    let worker = &mut self.workers[index];
+                    if !worker.started {
+                        tracing::info!("Worker {} is started", worker.name);
+                        worker.started = true;
+                        self.reporter.emit(LifecycleEvent::WorkerStarted { worker: worker.name.clone() });
+                        self.start_pending_workers();
+                    }
+                }
+                entry = self.worker_rx.recv(), if accepting => match entry {
+                    Some(entry) => {
+                        tracing::info!("Spawn worker {} added at runtime", entry.name);
+                        self.spawn(entry);
+                    }
+                    None => accepting = false,
+                },
+                _ = shutdown_rx.changed() => break,
+                () = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)),
+                    if next_deadline.is_some() => self.abort_timed_out_workers(),
+            }
+        }
+
+        self.shutdown(&mut shutdown_rx).await;
+        let shutdown_reason = shutdown_rx.borrow().as_ref().map(|shutdown| shutdown.reason.clone());
+
+        let workers = self
+            .workers
+            .into_iter()
+            .map(|SupervisedWorker { name, stopped, in_flight, .. }| {
+                let (outcome, runtime) = stopped.expect("all workers are stopped; qed");
+                let in_flight = *in_flight.borrow();
+                if in_flight > 0 {
+                    tracing::warn!(
+                        "Worker {name} is stopped with {in_flight} connections in flight"
+                    );
+                }
+                WorkerReport { name, outcome, runtime, in_flight }
+            })
+            .collect();
+        LifecycleReport { workers, shutdown_reason }
+    }
+
+    async fn shutdown(&mut self, shutdown_rx: &mut watch::Receiver<Option<Shutdown>>) {
+        // workers added while shutting down would not be signalled in order,
+        // spawn the ones already added and reject the others
+        self.worker_rx.close();
+        while let Ok(entry) = self.worker_rx.try_recv() {
+            self.spawn(entry);
+        }
+        for worker in self.workers.iter_mut().filter(|worker| worker.pending.is_some()) {
+            tracing::info!("Worker {} is not started before shutting down", worker.name);
+            worker.pending = None;
+            self.reporter.stopped(worker, &WorkerOutcome::NotStarted);
+            worker.stopped = Some((WorkerOutcome::NotStarted, Duration::ZERO));
+        }
+
+        for phase in self.shutdown_phases() {
+            let shutdown = shutdown_rx
+                .borrow_and_update()
+                .clone()
+                .unwrap_or(Shutdown::new(ShutdownReason::Dropped, Instant::now()));
+            if shutdown.escalated {
+                self.abort_workers();
+            }
+            for &index in phase.iter().filter(|&&index| self.workers[index].stopped.is_none()) {
+                let worker = &self.workers[index];
+                if let Err(_err) = worker.shutdown_tx.send(Some(worker.shutdown(&shutdown))) {
+                    tracing::warn!("Failed to send shutdown signal to worker {}", worker.name);
+                }
+            }
+
+            while phase.iter().any(|&index| self.workers[index].stopped.is_none()) {
+                let next_deadline = self.next_deadline();
+
+                tokio::select! {
+                    next = self.running_workers.next() => {
+                        let Some((index, result)) = next else { break };
+                        let _unused = self.on_stopped(index, result);
+                        self.stop_groups();
+                    }
+                    // e.g. a worker stops its group while it is shutting down
+                    Some(group_stop) = self.stop_rx.recv() => self.stop_group(group_stop),
+                    Ok(()) = shutdown_rx.changed() => {
+                        // e.g. the deadline is set by another shutdown signal
+                        let Some(shutdown) = shutdown_rx.borrow_and_update().clone() else {
+                            continue;
+                        };
+                        if shutdown.escalated {
+                            self.abort_workers();
+                        }
+                        for worker in self.workers.iter().filter(|worker| worker.stopped.is_none()) {
+                            worker.shutdown_tx.send_if_modified(|current| {
+                                current.is_some() && {
+                                    *current = Some(worker.shutdown(&shutdown));
+                                    true
+                                }
+                            });
+                        }
+                    }
+                    () = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)),
+                        if next_deadline.is_some() => self.abort_timed_out_workers(),
+                }
+            }
+        }
+    }
+
+    fn stop_group(&mut self, GroupStop { group, stopped_tx }: GroupStop) {
+        tracing::info!("Stop workers of group {group}");
+        match self.stopping_groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, waiters)) => waiters.push(stopped_tx),
+            None => self.stopping_groups.push((group, vec![stopped_tx])),
+        }
+        self.stop_groups();
+    }
+
+    /// Signals the workers of the stopping groups whose dependents are
+    /// stopped, and answers for the groups which are stopped.
+    ///
+    /// The workers depending on a worker of a group, directly or not, are
+    /// stopped with the group even if they are not in it.
+    fn stop_groups(&mut self) {
+        let mut stopping_groups = std::mem::take(&mut self.stopping_groups);
+        stopping_groups.retain_mut(|(group, waiters)| {
+            let members = self.group_members(group);
+
+            for &index in &members {
+                let worker = &mut self.workers[index];
+                if worker.pending.take().is_some() {
+                    self.reporter.stopped(worker, &WorkerOutcome::NotStarted);
+                    worker.stopped = Some((WorkerOutcome::NotStarted, Duration::ZERO));
+                }
+            }
+
+            let mut stopped = true;
+            for worker in members.iter().map(|&index| &self.workers[index]) {
+                if worker.stopped.is_some() {
+                    continue;
+                }
+                stopped = false;
+
+                let dependents_stopped = self.workers.iter().all(|dependent| {
+                    dependent.stopped.is_some()
+                        || !dependent.options.dependencies().contains(&worker.name)
+                });
+                if dependents_stopped && worker.shutdown_tx.borrow().is_none() {
+                    let shutdown = Shutdown::new(
+                        ShutdownReason::GroupStopped { group: group.clone() },
+                        Instant::now(),
+                    );
+                    if let Err(_err) = worker.shutdown_tx.send(Some(worker.shutdown(&shutdown))) {
+                        tracing::warn!("Failed to send shutdown signal to worker {}", worker.name);
+                    }
+                }
+            }
+
+            if stopped {
+                tracing::info!("Workers of group {group} are stopped");
+                for waiter in waiters.drain(..) {
+                    let _unused = waiter.send(());
+                }
+            }
+            !stopped
+        });
+        self.stopping_groups = stopping_groups;
+    }
+
+    /// Returns the indices of the workers in `group` and of the workers
+    /// depending on them, directly or not.
+    fn group_members(&self, group: &str) -> Vec<usize> {
+        let mut members: Vec<usize> = (0..self.workers.len())
+            .filter(|&index| self.workers[index].options.group() == Some(group))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, worker) in self.workers.iter().enumerate() {
+                let depends_on_member = worker.options.dependencies().iter().any(|dependency| {
+                    members.iter().any(|&member| self.workers[member].name == *dependency)
+                });
+                if depends_on_member && !members.contains(&index) {
+                    members.push(index);
+                    changed = true;
+                }
+            }
+        }
+        members
+    }
+
+    /// Returns the earliest instant at which a signalled worker is aborted.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.workers
+            .iter()
+            .filter(|worker| worker.stopped.is_none() && !worker.timed_out)
+            .filter_map(SupervisedWorker::deadline)
+            .min()
+    }
+
+    fn abort_workers(&self) {
+        for worker in self.workers.iter().filter(|worker| worker.stopped.is_none()) {
+            if let Some(abort_handle) = &worker.abort_handle {
+                tracing::warn!("Abort worker {}", worker.name);
+                abort_handle.abort();
+            }
+        }
+    }
+
+    fn abort_timed_out_workers(&mut self) {
+        let now = Instant::now();
+        for worker in self.workers.iter_mut().filter(|worker| {
+            worker.stopped.is_none()
+                && !worker.timed_out
+                && worker.deadline().is_some_and(|deadline| deadline <= now)
+        }) {
+            tracing::warn!("Worker {} is not stopped in time, abort it", worker.name);
+            if let Some(abort_handle) = &worker.abort_handle {
+                abort_handle.abort();
+            }
+            worker.timed_out = true;
+        }
+    }
+
+    fn shutdown_phases(&self) -> Vec<Vec<usize>> {
+        let workers =
+            self.workers.iter().map(|worker| (worker.name.as_str(), worker.options.dependencies()));
+        match DependencyGraph::new(workers) {
+            Ok(dependency_graph) => dependency_graph.shutdown_phases(),
+            Err(err) => {
+                tracing::warn!("Shut down all workers at once, error: {err}");
+                vec![(0..self.workers.len()).collect()]
+            }
+        }
+    }
+
+    /// Records the outcome of a stopped worker, returns `true` if all workers
+    /// should be shut down because of it.
+    fn on_stopped(&mut self, index: usize, result: Result<Result<(), E>, JoinError>) -> bool {
+        let worker = &mut self.workers[index];
+        let outcome = match result {
+            Ok(Ok(())) => WorkerOutcome::Ok,
+            Ok(Err(err)) => WorkerOutcome::Error(err),
+            Err(err) if err.is_cancelled() && worker.timed_out => WorkerOutcome::TimedOut,
+            Err(err) if err.is_cancelled() => WorkerOutcome::Cancelled,
+            Err(err) => WorkerOutcome::Panicked(panic_message(&*err.into_panic())),
+        };
+
+        let fail_fast = self.failure_policy == FailurePolicy::FailFast
+            && worker.options.critical()
+            && matches!(outcome, WorkerOutcome::Error(_) | WorkerOutcome::Panicked(_));
+
+        let worker_name = &worker.name;
+        match outcome {
+            WorkerOutcome::Ok => tracing::info!("Worker {worker_name} is stopped"),
+            WorkerOutcome::Panicked(ref message) => {
+                tracing::error!("Worker {worker_name} panicked: {message}");
+            }
+            _ => tracing::warn!("Worker {worker_name} is not stopped gracefully, {outcome}"),
+        }
+        if fail_fast {
+            tracing::error!("Critical worker {worker_name} is failed, shut down all workers");
+        }
+        self.reporter.stopped(worker, &outcome);
+
+        worker.stopped = Some((outcome, worker.started_at.elapsed()));
+        fail_fast
+    }
+}
+
+/// Tells the event subscribers, the metrics recorder, the circuit breaker and
+/// the receivers of
+/// [`workers_started`](crate::LifecycleManager::workers_started) what happens
+/// to the workers.
+#[derive(Clone)]
+pub(crate) struct Reporter {
+    event_tx: broadcast::Sender<LifecycleEvent>,
+    metrics: Metrics,
+    failure_window: Option<Arc<Mutex<FailureWindow>>>,
+    workers_started: Arc<watch::Sender<bool>>,
+}
+
+impl Reporter {
+    pub fn new(
+        event_tx: broadcast::Sender<LifecycleEvent>,
+        metrics: Metrics,
+        circuit_breaker: Option<CircuitBreaker>,
+        workers_started: watch::Sender<bool>,
+    ) -> Self {
+        Self {
+            event_tx,
+            metrics,
+            failure_window: circuit_breaker
+                .map(|circuit_breaker| Arc::new(Mutex::new(FailureWindow::new(circuit_breaker)))),
+            workers_started: Arc::new(workers_started),
+        }
+    }
+
+    fn emit(&self, event: LifecycleEvent) { events::emit(&self.event_tx, event); }
+
+    /// Records a failure of a worker, returns the circuit breaker if it is
+    /// tripped by it.
+    fn failed(&self) -> Option<CircuitBreaker> {
+        let failure_window = self.failure_window.as_ref()?;
+        let mut failure_window = failure_window.lock().expect("lock is not poisoned; qed");
+        failure_window.record().then(|| failure_window.circuit_breaker())
+    }
+
+    /// Reports that `worker` is stopped for good.
+    fn stopped<E>(&self, worker: &SupervisedWorker<E>, outcome: &WorkerOutcome<E>)
+    where
+        E: std::error::Error,
+    {
+        let shutdown_duration =
+            worker.shutdown_tx.borrow().as_ref().map(|shutdown| shutdown.started_at.elapsed());
+        self.metrics.record(|recorder| {
+            recorder.set_worker_up(&worker.name, false);
+            recorder.set_worker_exit_reason(&worker.name, outcome.kind());
+            if let Some(shutdown_duration) = shutdown_duration {
+                recorder.record_worker_shutdown_duration(&worker.name, shutdown_duration);
+            }
+        });
+        self.emit(LifecycleEvent::WorkerStopped {
+            worker: worker.name.clone(),
+            outcome: outcome.to_string(),
+        });
+    }
+}
+
+impl<E> SupervisedWorker<E> {
+    /// Returns the shutdown sent to this worker, with the earlier of the
+    /// global deadline and the one of this worker.
+    fn shutdown(&self, shutdown: &Shutdown) -> Shutdown {
+        let deadline = self
+            .options
+            .shutdown_timeout()
+            .map(|timeout| shutdown.started_at + timeout)
+            .into_iter()
+            .chain(shutdown.deadline)
+            .min();
+        Shutdown { deadline, ..shutdown.clone() }
+    }
+
+    /// Returns the instant at which this worker is aborted, if it is
+    /// signalled.
+    fn deadline(&self) -> Option<Instant> {
+        self.shutdown_tx.borrow().as_ref().and_then(|shutdown| {
+            let timeout = self.options.shutdown_timeout()?;
+            Some(shutdown.started_at + timeout)
+        })
+    }
+}
+
+/// Runs a worker, restarting it according to `restart_policy` until it is
+/// signalled to shut down.
+///
+/// A panic of the worker counts as a failure, it is resumed once the worker is
+/// not restarted anymore so that it is reported by the join handle.
+async fn supervise<E>(
+    name: String,
+    mut worker_fn: WorkerFn<E>,
+    restart_policy: RestartPolicy,
+    shutdown_signal: ShutdownSignal,
+    shutdown_trigger: mpsc::UnboundedSender<ShutdownReason>,
+    reporter: Reporter,
+) -> Result<(), E>
+where
+    E: std::error::Error + Send + 'static,
+{
+    let mut restarts = 0;
+    loop {
+        let run_signal = shutdown_signal.renew();
+        let result = AssertUnwindSafe(async { worker_fn(run_signal).await }).catch_unwind().await;
+        let stop = |result| match result {
+            Ok(result) => result,
+            Err(payload) => std::panic::resume_unwind(payload),
+        };
+        let error = match &result {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(err.to_string()),
+            Err(payload) => Some(format!("panicked: {}", panic_message(&**payload))),
+        };
+        if let Some(error) = error {
+            reporter.emit(LifecycleEvent::WorkerFailed { worker: name.clone(), error });
+
+            // failures while shutting down do not count
+            if shutdown_signal.reason().is_none() {
+                if let Some(circuit_breaker) = reporter.failed() {
+                    let reason = ShutdownReason::CircuitBreakerTripped {
+                        max_failures: circuit_breaker.max_failures(),
+                        window: circuit_breaker.window(),
+                    };
+                    tracing::error!("Worker {name} is failed, {reason}, shut down all workers");
+                    reporter.emit(LifecycleEvent::CircuitBreakerTripped { worker: name.clone() });
+                    let _unused = shutdown_trigger.send(reason);
+                }
+            }
+        }
+
+        if shutdown_signal.reason().is_some() {
+            return stop(result);
+        }
+
+        match restart_policy.decide(!matches!(result, Ok(Ok(()))), restarts) {
+            RestartDecision::Stop => return stop(result),
+            RestartDecision::Escalate => {
+                tracing::error!("Worker {name} exceeded its restart limit, shut down all workers");
+                let _unused = shutdown_trigger.send(ShutdownReason::WorkerFailed { worker: name });
+                return stop(result);
+            }
+            RestartDecision::Restart(delay) => {
+                match &result {
+                    Ok(Ok(())) => tracing::info!("Worker {name} is stopped, restart in {delay:?}"),
+                    Ok(Err(err)) => {
+                        tracing::warn!(
+                            "Worker {name} is failed, restart in {delay:?}, error: {err}"
+                        );
+                    }
+                    Err(payload) => {
+                        let message = panic_message(&**payload);
+                        tracing::warn!("Worker {name} panicked, restart in {delay:?}: {message}");
+                    }
+                }
+                let exit_reason = match &result {
+                    Ok(Ok(())) => "ok",
+                    Ok(Err(_)) => "error",
+                    Err(_) => "panicked",
+                };
+                reporter.metrics.record(|recorder| {
+                    recorder.set_worker_up(&name, false);
+                    recorder.set_worker_exit_reason(&name, exit_reason);
+                });
+
+                tokio::select! {
+                    () = tokio::time::sleep(delay) => {}
+                    _ = shutdown_signal.renew() => return stop(result),
+                }
+                restarts += 1;
+                reporter.metrics.record(|recorder| {
+                    recorder.increment_worker_restarts(&name);
+                    recorder.set_worker_up(&name, true);
+                });
+                reporter.emit(LifecycleEvent::WorkerRestarted { worker: name.clone(), restarts });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::future::BoxFuture;
+    use tokio::{
+        sync::{broadcast, mpsc, watch},
+        time::Instant,
+    };
+
+    use super::{FailurePolicy, Reporter, Supervisor};
+    use crate::{
+        metrics::Metrics, shutdown_signal::Shutdown, worker::WorkerEntry, ShutdownReason,
+        ShutdownSignal, WorkerOptions, WorkerOutcome,
+    };
+
+    fn worker(name: &str, drain: Duration) -> WorkerEntry<std::io::Error> {
+        WorkerEntry::new_once(name, WorkerOptions::new(), move |shutdown_signal: ShutdownSignal| {
+            Box::pin(async move {
+                shutdown_signal.await;
+                tokio::time::sleep(drain).await;
+                Ok(())
+            }) as BoxFuture<'static, _>
+        })
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_abort_workers_on_escalation() {
+        let (_worker_tx, worker_rx) = mpsc::unbounded_channel();
+        let (_stop_tx, stop_rx) = mpsc::unbounded_channel();
+        let (shutdown_trigger, _trigger_rx) = mpsc::unbounded_channel::<ShutdownReason>();
+        let (shutdown_tx, shutdown_rx) = watch::channel(None);
+
+        let (event_tx, _) = broadcast::channel(16);
+        let mut supervisor = Supervisor::new(
+            worker_rx,
+            stop_rx,
+            shutdown_trigger,
+            Reporter::new(event_tx, Metrics::default(), None, watch::channel(false).0),
+            FailurePolicy::Continue,
+        );
+        supervisor.spawn(worker("fast", Duration::ZERO));
+        supervisor.spawn(worker("stuck", Duration::from_secs(3600)));
+        let join_handle = tokio::spawn(supervisor.serve(shutdown_rx));
+
+        shutdown_tx.send_replace(Some(Shutdown::new(ShutdownReason::Custom, Instant::now())));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown_tx.send_modify(|shutdown| {
+            shutdown.as_mut().expect("shutdown is started; qed").escalated = true;
+        });
+
+        let report = join_handle.await.unwrap();
+        assert!(report.workers[0].outcome.is_ok());
+        assert!(matches!(report.workers[1].outcome, WorkerOutcome::Cancelled));
+        let cancelled: Vec<_> = report.cancelled().map(|worker| worker.name.as_str()).collect();
+        assert_eq!(cancelled, ["stuck"]);
+    }
+}
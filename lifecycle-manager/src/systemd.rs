@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use snafu::ResultExt;
+use tokio::{
+    net::UnixDatagram,
+    sync::watch,
+    time::{Instant, Interval},
+};
+
+use crate::{
+    error::{NotifySystemdSnafu, Result},
+    Error, ShutdownSignal, ShutdownState, Worker,
+};
+
+#[derive(Default)]
+struct Registry {
+    pings: BTreeMap<String, Instant>,
+}
+
+/// A worker speaking the `sd_notify` protocol of systemd over
+/// `$NOTIFY_SOCKET`, created by
+/// [`LifecycleManager::systemd_notifier`](crate::LifecycleManager::systemd_notifier).
+///
+/// It sends `READY=1` once all workers of the lifecycle manager are started,
+/// see
+/// [`WorkerOptions::with_started_notification`](crate::WorkerOptions::with_started_notification),
+/// `STOPPING=1` as soon as the [`ShutdownState`] leaves
+/// [`WaitForSignal`](ShutdownState::WaitForSignal), and `WATCHDOG=1` every
+/// half watchdog interval as long as every [`WatchdogHandle`] is pinged
+/// within the interval. Let other workers
+/// [depend on](crate::WorkerOptions::depends_on) this worker to keep the
+/// watchdog pinged while they drain.
+pub struct SystemdNotifier<E> {
+    socket_path: PathBuf,
+    watchdog_interval: Option<Duration>,
+    shutdown_state: watch::Receiver<ShutdownState>,
+    workers_started: watch::Receiver<bool>,
+    registry: Arc<Mutex<Registry>>,
+    _error: PhantomData<fn() -> E>,
+}
+
+impl<E> SystemdNotifier<E> {
+    /// Creates a notifier sending to the unix datagram socket at
+    /// `socket_path`, with the receivers of
+    /// [`LifecycleManager::shutdown_state`](crate::LifecycleManager::shutdown_state)
+    /// and
+    /// [`LifecycleManager::workers_started`](crate::LifecycleManager::workers_started).
+    /// Abstract socket addresses are not supported.
+    #[inline]
+    #[must_use]
+    pub fn new(
+        socket_path: impl Into<PathBuf>,
+        shutdown_state: watch::Receiver<ShutdownState>,
+        workers_started: watch::Receiver<bool>,
+    ) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            watchdog_interval: None,
+            shutdown_state,
+            workers_started,
+            registry: Arc::default(),
+            _error: PhantomData,
+        }
+    }
+
+    /// Creates a notifier from `$NOTIFY_SOCKET` and `$WATCHDOG_USEC`, returns
+    /// `None` if this process is not started by systemd with `Type=notify`.
+    #[must_use]
+    pub fn from_env(
+        shutdown_state: watch::Receiver<ShutdownState>,
+        workers_started: watch::Receiver<bool>,
+    ) -> Option<Self> {
+        let socket_path = env::var_os("NOTIFY_SOCKET")?;
+        if socket_path.to_string_lossy().starts_with('@') {
+            tracing::warn!("Abstract NOTIFY_SOCKET is not supported, do not notify systemd");
+            return None;
+        }
+
+        let notifier = Self::new(socket_path, shutdown_state, workers_started);
+        let watchdog_pid = env::var("WATCHDOG_PID").ok().and_then(|pid| pid.parse::<u32>().ok());
+        if watchdog_pid.is_some_and(|pid| pid != std::process::id()) {
+            return Some(notifier);
+        }
+        match env::var("WATCHDOG_USEC").ok().and_then(|usec| usec.parse().ok()) {
+            Some(usec) => Some(notifier.with_watchdog_interval(Duration::from_micros(usec))),
+            None => Some(notifier),
+        }
+    }
+
+    /// Sends `WATCHDOG=1` every half `watchdog_interval`, which should match
+    /// `WatchdogSec` of the service.
+    #[inline]
+    #[must_use]
+    pub const fn with_watchdog_interval(mut self, watchdog_interval: Duration) -> Self {
+        self.watchdog_interval = Some(watchdog_interval);
+        self
+    }
+
+    /// Registers a component named `name` which must be pinged within the
+    /// watchdog interval, otherwise `WATCHDOG=1` is not sent anymore and
+    /// systemd eventually restarts the service.
+    #[must_use]
+    pub fn watchdog_handle(&self, name: impl Into<String>) -> WatchdogHandle {
+        let name = name.into();
+        self.registry
+            .lock()
+            .expect("lock is not poisoned; qed")
+            .pings
+            .insert(name.clone(), Instant::now());
+        WatchdogHandle { name, registry: self.registry.clone() }
+    }
+}
+
+#[async_trait]
+impl<E> Worker for SystemdNotifier<E>
+where
+    E: From<Error> + Send,
+{
+    type Error = E;
+
+    fn name(&self) -> &str { "systemd-notifier" }
+
+    async fn serve(self, mut shutdown_signal: ShutdownSignal) -> Result<(), Self::Error> {
+        let Self {
+            socket_path,
+            watchdog_interval,
+            mut shutdown_state,
+            mut workers_started,
+            registry,
+            ..
+        } = self;
+        let socket = UnixDatagram::unbound().context(NotifySystemdSnafu)?;
+
+        let mut ready = false;
+        let mut stopping = false;
+        let mut watchdog = watchdog_interval
+            .filter(|interval| !interval.is_zero())
+            .map(|interval| tokio::time::interval(interval / 2));
+        loop {
+            if !ready && *workers_started.borrow_and_update() {
+                notify(&socket, &socket_path, "READY=1").await?;
+                ready = true;
+            }
+
+            let state = *shutdown_state.borrow_and_update();
+            if !stopping && !matches!(state, ShutdownState::Initial | ShutdownState::WaitForSignal)
+            {
+                notify(&socket, &socket_path, "STOPPING=1").await?;
+                stopping = true;
+            }
+
+            tokio::select! {
+                _ = &mut shutdown_signal => break,
+                Ok(()) = workers_started.changed(), if !ready => {}
+                Ok(()) = shutdown_state.changed(), if !stopping => {}
+                interval = tick(&mut watchdog) => {
+                    let stalled = registry
+                        .lock()
+                        .expect("lock is not poisoned; qed")
+                        .pings
+                        .iter()
+                        .filter(|(_, pinged_at)| pinged_at.elapsed() > interval)
+                        .map(|(name, _)| name.clone())
+                        .collect::<Vec<_>>();
+                    if stalled.is_empty() {
+                        notify(&socket, &socket_path, "WATCHDOG=1").await?;
+                    } else {
+                        tracing::warn!(
+                            "Workers {} are stalled, stop pinging the watchdog",
+                            stalled.join(", ")
+                        );
+                    }
+                }
+            }
+        }
+
+        if !stopping {
+            notify(&socket, &socket_path, "STOPPING=1").await?;
+        }
+        Ok(())
+    }
+}
+
+async fn notify(socket: &UnixDatagram, socket_path: &Path, state: &str) -> Result<()> {
+    tracing::debug!("Notify systemd with {state:?}");
+    socket.send_to(state.as_bytes(), socket_path).await.context(NotifySystemdSnafu)?;
+    Ok(())
+}
+
+/// Waits for the next tick of `watchdog`, returns the watchdog interval.
+async fn tick(watchdog: &mut Option<Interval>) -> Duration {
+    match watchdog {
+        Some(watchdog) => {
+            watchdog.tick().await;
+            watchdog.period() * 2
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// A handle for a worker to tell a [`SystemdNotifier`] that it is not
+/// stalled.
+#[derive(Clone)]
+pub struct WatchdogHandle {
+    name: String,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl WatchdogHandle {
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str { &self.name }
+
+    #[inline]
+    pub fn ping(&self) {
+        if let Some(pinged_at) =
+            self.registry.lock().expect("lock is not poisoned; qed").pings.get_mut(&self.name)
+        {
+            *pinged_at = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Helpers for testing code built on a [`LifecycleManager`] without sending
+//! signals to the test process, enabled with the `test-util` feature.
+//!
+//! Signals are fired with a [`SignalSender`] instead, so tests can run in
+//! parallel and under the paused clock of tokio, e.g. with
+//! `#[tokio::test(start_paused = true)]`.
+
+use futures::stream::{BoxStream, StreamExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+#[cfg(unix)]
+use crate::ReloadSignal;
+use crate::{
+    error::Result, signal_watcher::ReceivedSignal, LifecycleEvent, LifecycleEvents,
+    LifecycleManager, LifecycleReport, ShutdownReason, Signal,
+};
+
+/// Creates a [`SignalSender`] firing signals at the lifecycle manager or
+/// signal watcher which is given the [`SignalSource`].
+#[must_use]
+pub fn signal_source() -> (SignalSender, SignalSource) {
+    let (signal_tx, signal_rx) = mpsc::unbounded_channel();
+    (SignalSender { signal_tx }, SignalSource { signal_rx })
+}
+
+/// Fires synthetic signals, created by [`signal_source`].
+#[derive(Clone, Debug)]
+pub struct SignalSender {
+    signal_tx: mpsc::UnboundedSender<ReceivedSignal>,
+}
+
+impl SignalSender {
+    /// Fires `SIGTERM`.
+    #[inline]
+    pub fn terminate(&self) { self.signal(Signal::Terminate); }
+
+    /// Fires `SIGINT`.
+    #[inline]
+    pub fn interrupt(&self) { self.signal(Signal::Interrupt); }
+
+    /// Fires `SIGHUP`, which is ignored unless it is registered with
+    /// [`with_reload_signal`](LifecycleManager::with_reload_signal).
+    #[cfg(unix)]
+    #[inline]
+    pub fn hangup(&self) { self.reload(ReloadSignal::Hangup); }
+
+    /// Fires a shutdown signal.
+    #[inline]
+    pub fn signal(&self, signal: Signal) {
+        self.send(ReceivedSignal::Shutdown(ShutdownReason::Signal(signal)));
+    }
+
+    /// Fires a reload signal, which is ignored unless it is registered with
+    /// [`with_reload_signal`](LifecycleManager::with_reload_signal).
+    #[cfg(unix)]
+    #[inline]
+    pub fn reload(&self, signal: ReloadSignal) { self.send(ReceivedSignal::Reload(signal)); }
+
+    fn send(&self, signal: ReceivedSignal) {
+        if let Err(_err) = self.signal_tx.send(signal) {
+            tracing::warn!("Signal watcher is stopped, the signal is not received");
+        }
+    }
+}
+
+/// Receives the signals of a [`SignalSender`], given to
+/// [`LifecycleManager::with_signal_source`] or
+/// [`SignalWatcherBuilder::with_signal_source`](crate::SignalWatcherBuilder::with_signal_source).
+#[derive(Debug)]
+pub struct SignalSource {
+    signal_rx: mpsc::UnboundedReceiver<ReceivedSignal>,
+}
+
+impl SignalSource {
+    pub(crate) fn into_stream(self) -> BoxStream<'static, ReceivedSignal> {
+        UnboundedReceiverStream::new(self.signal_rx).boxed()
+    }
+}
+
+/// Serves a [`LifecycleManager`] receiving synthetic signals, and records the
+/// [`LifecycleEvent`]s for asserting on them along with the report.
+pub struct TestHarness<E> {
+    lifecycle_manager: LifecycleManager<E>,
+    signal_sender: SignalSender,
+    events: LifecycleEvents,
+}
+
+impl<E> TestHarness<E>
+where
+    E: std::error::Error + Send + 'static,
+{
+    /// Wraps `lifecycle_manager`, which receives signals only from the
+    /// [`signal_sender`](Self::signal_sender) afterwards.
+    #[must_use]
+    pub fn new(lifecycle_manager: LifecycleManager<E>) -> Self {
+        let (signal_sender, signal_source) = signal_source();
+        let lifecycle_manager = lifecycle_manager.with_signal_source(signal_source);
+        let events = lifecycle_manager.events();
+        Self { lifecycle_manager, signal_sender, events }
+    }
+
+    /// Returns a sender for firing signals, which can be moved into workers or
+    /// other tasks.
+    #[inline]
+    #[must_use]
+    pub fn signal_sender(&self) -> SignalSender { self.signal_sender.clone() }
+
+    /// Serves the lifecycle manager until its workers are stopped, returns the
+    /// report and the events sent meanwhile, in order.
+    ///
+    /// # Errors
+    ///
+    /// If [`LifecycleManager::serve`] fails.
+    pub async fn serve(self) -> Result<(LifecycleReport<E>, Vec<LifecycleEvent>)> {
+        let Self { lifecycle_manager, mut events, .. } = self;
+        let collector = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            while let Some(event) = events.recv().await {
+                collected.push(event);
+            }
+            collected
+        });
+
+        let report = lifecycle_manager.serve().await?;
+        // the events are closed once the signal watcher and the workers are dropped
+        let events = collector.await.expect("collector does not panic; qed");
+        Ok((report, events))
+    }
+}
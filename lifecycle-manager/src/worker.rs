@@ -1,6 +1,15 @@
+#[cfg(feature = "periodic")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+#[cfg(feature = "periodic")]
+use futures::FutureExt;
 
-use crate::ShutdownSignal;
+#[cfg(feature = "periodic")]
+use crate::{periodic, Error, Schedule};
+use crate::{RestartPolicy, ShutdownSignal};
 
 #[async_trait]
 pub trait Worker {
@@ -10,3 +19,194 @@ pub trait Worker {
 
     async fn serve(self, shutdown_signal: ShutdownSignal) -> Result<(), Self::Error>;
 }
+
+/// Options applied to a worker when it is added to a
+/// [`LifecycleManager`](crate::LifecycleManager).
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    dependencies: Vec<String>,
+    group: Option<String>,
+    restart_policy: RestartPolicy,
+    critical: bool,
+    started_notification: bool,
+    shutdown_timeout: Option<Duration>,
+}
+
+impl Default for WorkerOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            dependencies: Vec::new(),
+            group: None,
+            restart_policy: RestartPolicy::default(),
+            critical: true,
+            started_notification: false,
+            shutdown_timeout: None,
+        }
+    }
+}
+
+impl WorkerOptions {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self { Self::default() }
+
+    /// Declares that the worker depends on the worker named `name`.
+    ///
+    /// On shutdown, a worker is only signalled after every worker depending
+    /// on it has stopped.
+    #[inline]
+    #[must_use]
+    pub fn depends_on(mut self, name: impl Into<String>) -> Self {
+        self.dependencies.push(name.into());
+        self
+    }
+
+    /// Adds the worker to the group named `group`, which can be stopped on its
+    /// own with a [`WorkerGroup`](crate::WorkerGroup).
+    #[inline]
+    #[must_use]
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_restart_policy(mut self, restart_policy: RestartPolicy) -> Self {
+        self.restart_policy = restart_policy;
+        self
+    }
+
+    /// Sets whether a failure of the worker shuts down all workers when the
+    /// lifecycle manager uses
+    /// [`FailurePolicy::FailFast`](crate::FailurePolicy::FailFast). Workers are
+    /// critical by default.
+    #[inline]
+    #[must_use]
+    pub const fn with_critical(mut self, critical: bool) -> Self {
+        self.critical = critical;
+        self
+    }
+
+    /// Sets whether the worker reports that it is started with
+    /// [`ShutdownSignal::notify_started`]. Workers depending on it are only
+    /// spawned after that, other workers count as started once spawned.
+    #[inline]
+    #[must_use]
+    pub const fn with_started_notification(mut self, started_notification: bool) -> Self {
+        self.started_notification = started_notification;
+        self
+    }
+
+    /// Limits how long the worker may take to stop, measured from the first
+    /// shutdown signal or from the stop of its group. Once the timeout expires,
+    /// the worker is aborted and reported as
+    /// [`TimedOut`](crate::WorkerOutcome::TimedOut). Workers are waited for
+    /// without limit by default.
+    #[inline]
+    #[must_use]
+    pub const fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(shutdown_timeout);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dependencies(&self) -> &[String] { &self.dependencies }
+
+    #[inline]
+    #[must_use]
+    pub fn group(&self) -> Option<&str> { self.group.as_deref() }
+
+    #[inline]
+    #[must_use]
+    pub const fn critical(&self) -> bool { self.critical }
+
+    #[inline]
+    #[must_use]
+    pub const fn started_notification(&self) -> bool { self.started_notification }
+
+    #[inline]
+    #[must_use]
+    pub const fn restart_policy(&self) -> RestartPolicy { self.restart_policy }
+
+    #[inline]
+    #[must_use]
+    pub const fn shutdown_timeout(&self) -> Option<Duration> { self.shutdown_timeout }
+}
+
+pub(crate) type WorkerFn<E> =
+    Box<dyn FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send>;
+
+pub(crate) struct WorkerEntry<E> {
+    pub name: String,
+    pub options: WorkerOptions,
+    pub worker_fn: WorkerFn<E>,
+    /// Whether `worker_fn` can be called more than once.
+    pub restartable: bool,
+}
+
+impl<E> WorkerEntry<E> {
+    pub fn new(
+        name: &str,
+        options: WorkerOptions,
+        worker_fn: impl FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self {
+        Self { name: name.to_string(), options, worker_fn: Box::new(worker_fn), restartable: true }
+    }
+
+    pub fn new_once(
+        name: &str,
+        options: WorkerOptions,
+        worker_fn: impl FnOnce(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+    ) -> Self {
+        let mut worker_fn = Some(worker_fn);
+        Self {
+            name: name.to_string(),
+            options,
+            worker_fn: Box::new(move |shutdown_signal| {
+                let worker_fn = worker_fn.take().expect("worker is not restartable; qed");
+                worker_fn(shutdown_signal)
+            }),
+            restartable: false,
+        }
+    }
+
+    pub fn from_worker(
+        worker: impl Worker<Error = E> + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self {
+        let name = worker.name().to_string();
+        Self::new_once(&name, options, move |shutdown_signal| worker.serve(shutdown_signal))
+    }
+
+    #[cfg(feature = "periodic")]
+    pub fn periodic<J>(name: &str, schedule: Schedule, options: WorkerOptions, job: J) -> Self
+    where
+        J: FnMut(ShutdownSignal) -> BoxFuture<'static, Result<(), E>> + Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        // the job is shared by the runs of the worker and kept across restarts
+        let job = Arc::new(Mutex::new(job));
+        let worker_name = name.to_string();
+        Self::new(name, options, move |shutdown_signal| {
+            let (name, schedule) = (worker_name.clone(), schedule.clone());
+            periodic::run_periodic(name, schedule, job.clone(), shutdown_signal).boxed()
+        })
+    }
+
+    pub fn from_factory<W>(
+        mut factory: impl FnMut() -> W + Send + 'static,
+        options: WorkerOptions,
+    ) -> Self
+    where
+        W: Worker<Error = E> + Send + 'static,
+    {
+        let mut worker = Some(factory());
+        let name = worker.as_ref().map(|worker| worker.name().to_string()).unwrap_or_default();
+        Self::new(&name, options, move |shutdown_signal| {
+            worker.take().unwrap_or_else(&mut factory).serve(shutdown_signal)
+        })
+    }
+}